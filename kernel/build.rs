@@ -0,0 +1,79 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+// 用户程序的目标平台，与内核相同
+const TARGET: &str = "riscv64gc-unknown-none-elf";
+
+fn main() {
+    let manifest_dir = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
+    let user_dir = manifest_dir.join("..").join("user");
+    for path in ["src", "Cargo.toml", "build.rs", ".cargo"] {
+        println!("cargo:rerun-if-changed={}", user_dir.join(path).display());
+    }
+    // 链接脚本不是 Rust 源文件，修改之后也要重新链接内核
+    println!("cargo:rerun-if-changed=src/linker.ld");
+
+    build_user_apps(&user_dir);
+
+    let app_dir = user_dir.join("target").join(TARGET).join("release");
+    let apps = app_names(&user_dir.join("src").join("bin"));
+    let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
+    fs::write(out_dir.join("link_app.S"), link_app_asm(&apps, &app_dir)).unwrap();
+}
+
+/// 在 user 目录下单独运行一次 cargo，编译出所有用户程序的 ELF
+fn build_user_apps(user_dir: &Path) {
+    let cargo = env::var("CARGO").unwrap_or_else(|_| "cargo".into());
+    let status = Command::new(cargo)
+        .args(["build", "--release"])
+        .current_dir(user_dir)
+        // 这些环境变量是 cargo 为内核设置的，不能传给用户程序的编译
+        // (否则用户程序也会用内核的链接脚本，或者被 clippy 检查)
+        .env_remove("CARGO_ENCODED_RUSTFLAGS")
+        .env_remove("RUSTFLAGS")
+        .env_remove("RUSTC_WORKSPACE_WRAPPER")
+        .status()
+        .expect("failed to run cargo for user apps");
+    assert!(status.success(), "failed to build user apps");
+}
+
+/// src/bin 下的每个文件都是一个用户程序，按文件名排序，序号就是应用编号
+fn app_names(bin_dir: &Path) -> Vec<String> {
+    let mut apps: Vec<String> = fs::read_dir(bin_dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "rs"))
+        .map(|path| path.file_stem().unwrap().to_string_lossy().into_owned())
+        .collect();
+    apps.sort();
+    apps
+}
+
+/// 生成 link_app.S：把所有用户程序的 ELF 嵌入内核的 .data 段
+/// _num_app 之后依次是应用数量、每个应用的起始地址，以及最后一个应用的结束地址
+fn link_app_asm(apps: &[String], app_dir: &Path) -> String {
+    let mut asm = String::new();
+    asm.push_str("    .align 3\n    .section .data\n    .global _num_app\n_num_app:\n");
+    asm.push_str(&format!("    .quad {}\n", apps.len()));
+    for i in 0..apps.len() {
+        asm.push_str(&format!("    .quad app_{}_start\n", i));
+    }
+    if let Some(last) = apps.len().checked_sub(1) {
+        asm.push_str(&format!("    .quad app_{}_end\n", last));
+    }
+
+    asm.push_str("\n    .global _app_names\n_app_names:\n");
+    for app in apps {
+        asm.push_str(&format!("    .string \"{}\"\n", app));
+    }
+
+    for (i, app) in apps.iter().enumerate() {
+        asm.push_str(&format!(
+            "\n    .section .data\n    .global app_{i}_start\n    .global app_{i}_end\n    .align 3\napp_{i}_start:\n    .incbin \"{}\"\napp_{i}_end:\n",
+            app_dir.join(app).display()
+        ));
+    }
+    asm
+}
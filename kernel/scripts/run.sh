@@ -0,0 +1,12 @@
+#!/bin/sh
+# cargo run 的 runner：先把函数符号表写入内核 (用于 panic 时的栈回溯)，再用 QEMU 启动
+# 参数：内核 ELF 的路径
+set -e
+
+KERNEL=$(realpath "$1")
+TOOLS_DIR=$(cd "$(dirname "$0")/../../tools/ksym" && pwd)
+
+# 在 tools/ksym 目录下运行 cargo，避免用上内核的 .cargo/config.toml (目标平台是 riscv)
+(cd "$TOOLS_DIR" && cargo run --quiet --release -- "$KERNEL")
+
+exec qemu-system-riscv64 -machine virt -nographic -bios default -kernel "$KERNEL"
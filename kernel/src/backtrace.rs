@@ -0,0 +1,92 @@
+use crate::ksym;
+use crate::task;
+use core::arch::asm;
+
+// 栈回溯：内核编译时打开了 force-frame-pointers，每个函数的栈帧中
+//   fp - 8  保存着返回地址 ra
+//   fp - 16 保存着调用者的 fp
+// 沿着这条链向上走，直到 fp 离开当前所在的内核栈
+
+// 最多打印多少层
+const MAX_DEPTH: usize = 64;
+
+extern "C" {
+    // entry.asm 中定义的启动栈
+    static boot_stack_lower_bound: u8;
+    static boot_stack_top: u8;
+}
+
+/// 包含地址 addr 的内核栈 [bottom, top)：启动栈或者某个任务的内核栈
+fn stack_bounds(addr: usize) -> Option<(usize, usize)> {
+    let boot_stack = unsafe {
+        (
+            &boot_stack_lower_bound as *const u8 as usize,
+            &boot_stack_top as *const u8 as usize,
+        )
+    };
+    if (boot_stack.0..boot_stack.1).contains(&addr) {
+        return Some(boot_stack);
+    }
+    task::kernel_stack_containing(addr)
+}
+
+/// 把调用栈上各层的返回地址依次存入 frames，返回存入的层数
+/// 第 0 层是调用 capture 的函数中的位置
+#[inline(never)]
+pub fn capture(frames: &mut [usize]) -> usize {
+    let mut fp: usize;
+    unsafe { asm!("mv {}, s0", out(reg) fp) };
+    let Some((bottom, top)) = stack_bounds(fp) else {
+        return 0;
+    };
+    let mut depth = 0;
+    while depth < frames.len() {
+        // 保存 ra 和 fp 的位置必须在栈内
+        if fp < bottom + 16 || fp > top || fp % 8 != 0 {
+            break;
+        }
+        let ra = unsafe { *((fp - 8) as *const usize) };
+        let prev_fp = unsafe { *((fp - 16) as *const usize) };
+        if ra == 0 {
+            break;
+        }
+        frames[depth] = ra;
+        depth += 1;
+        // 调用者的栈帧在更高的地址
+        if prev_fp <= fp {
+            break;
+        }
+        fp = prev_fp;
+    }
+    depth
+}
+
+/// 打印 capture 得到的返回地址
+pub fn print_frames(frames: &[usize]) {
+    if !ksym::available() {
+        println!("  (no symbol table, run tools/ksym on the kernel image to embed one)");
+    }
+    for (depth, &ra) in frames.iter().enumerate() {
+        // ra 指向调用指令的下一条，减 1 才一定落在调用者的函数内
+        match ksym::lookup(ra - 1) {
+            Some((name, offset)) => {
+                println!("  #{:<2} {:#x} {}+{:#x}", depth, ra, name, offset + 1)
+            }
+            None => println!("  #{:<2} {:#x}", depth, ra),
+        }
+    }
+}
+
+/// 打印当前的调用栈
+#[inline(never)]
+pub fn backtrace() {
+    let mut frames = [0; MAX_DEPTH + 1];
+    let depth = capture(&mut frames);
+    if depth == 0 {
+        println!("backtrace: fp is not on a kernel stack");
+        return;
+    }
+    println!("backtrace:");
+    // 跳过 backtrace 自己
+    print_frames(&frames[1..depth]);
+}
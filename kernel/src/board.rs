@@ -0,0 +1,203 @@
+use crate::config::MAX_HARTS;
+use fdt::Fdt;
+use spin::Once;
+
+// 板级信息：启动时从 OpenSBI 传来的设备树 (DTB) 中解析出内核关心的设备
+// 解析结果只保存数值，之后不再访问 DTB 所在的内存
+
+/// 一段 MMIO 寄存器区域 [base, base + size)
+#[derive(Debug, Clone, Copy)]
+pub struct MmioRegion {
+    pub base: usize,
+    pub size: usize,
+}
+
+/// 串口控制器 (ns16550a)
+#[derive(Debug, Clone, Copy)]
+pub struct UartInfo {
+    pub region: MmioRegion,
+    pub irq: Option<usize>, // 在 PLIC 上的中断号
+}
+
+/// 平台级中断控制器 (PLIC)
+#[derive(Debug, Clone, Copy)]
+pub struct PlicInfo {
+    pub region: MmioRegion,
+    pub ndev: usize, // 支持的中断源数量 (中断号 1..=ndev)
+    // 每个 hart 的 S 模式外部中断对应的 PLIC context 编号
+    pub s_contexts: [Option<usize>; MAX_HARTS],
+}
+
+/// 实时时钟 (Goldfish RTC)
+#[derive(Debug, Clone, Copy)]
+pub struct RtcInfo {
+    pub region: MmioRegion,
+}
+
+// 内核命令行的最大长度，超出的部分被截断
+const BOOTARGS_MAX: usize = 256;
+
+/// 内核命令行 (/chosen 节点的 bootargs)，复制一份保存下来
+pub struct BootArgs {
+    buf: [u8; BOOTARGS_MAX],
+    len: usize,
+}
+
+impl BootArgs {
+    fn new(args: &str) -> Self {
+        // 截断时不能切在 UTF-8 字符中间
+        let mut len = args.len().min(BOOTARGS_MAX);
+        while !args.is_char_boundary(len) {
+            len -= 1;
+        }
+        let mut buf = [0; BOOTARGS_MAX];
+        buf[..len].copy_from_slice(&args.as_bytes()[..len]);
+        Self { buf, len }
+    }
+
+    pub fn as_str(&self) -> &str {
+        core::str::from_utf8(&self.buf[..self.len]).unwrap()
+    }
+}
+
+impl core::fmt::Debug for BootArgs {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(f, "{:?}", self.as_str())
+    }
+}
+
+// QEMU virt 平台的时钟频率，设备树中没有 timebase-frequency 时使用
+const DEFAULT_TIMEBASE_FREQ: usize = 10_000_000;
+
+#[derive(Debug)]
+pub struct BoardInfo {
+    pub uart: Option<UartInfo>,
+    pub plic: Option<PlicInfo>,
+    pub rtc: Option<RtcInfo>,
+    pub bootargs: BootArgs,
+    pub timebase_freq: usize, // time CSR 每秒增加的次数
+}
+
+static BOARD_INFO: Once<BoardInfo> = Once::new();
+
+/// 第一个带 reg 属性的寄存器区域
+fn first_region(node: fdt::node::FdtNode) -> Option<MmioRegion> {
+    let reg = node.reg()?.next()?;
+    Some(MmioRegion {
+        base: reg.starting_address as usize,
+        size: reg.size.unwrap_or(0),
+    })
+}
+
+// CPU 本地中断控制器上的中断号：S 模式外部中断
+const IRQ_S_EXT: usize = 9;
+
+/// 找到 phandle 为 intc_phandle 的 CPU 本地中断控制器属于哪个 hart
+fn hart_of_intc(fdt: &Fdt, intc_phandle: usize) -> Option<usize> {
+    fdt.find_node("/cpus")?
+        .children()
+        .filter(|cpu| cpu.name.starts_with("cpu@"))
+        .find(|cpu| {
+            cpu.children().any(|intc| {
+                intc.name == "interrupt-controller"
+                    && intc.property("phandle").and_then(|p| p.as_usize()) == Some(intc_phandle)
+            })
+        })
+        .and_then(|cpu| Some(cpu.reg()?.next()?.starting_address as usize))
+}
+
+fn parse_plic(fdt: &Fdt) -> Option<PlicInfo> {
+    let node = fdt.find_compatible(&["riscv,plic0", "sifive,plic-1.0.0"])?;
+    let ndev = node.property("riscv,ndev")?.as_usize()?;
+
+    // interrupts-extended 由 <phandle 中断号> 组成，第 i 项就是 PLIC 的第 i 个 context
+    let mut s_contexts = [None; MAX_HARTS];
+    let mut cells = node
+        .property("interrupts-extended")?
+        .value
+        .as_chunks::<4>()
+        .0
+        .iter()
+        .map(|&cell| u32::from_be_bytes(cell) as usize);
+    let mut context = 0;
+    while let (Some(phandle), Some(irq)) = (cells.next(), cells.next()) {
+        if irq == IRQ_S_EXT {
+            if let Some(hart) = hart_of_intc(fdt, phandle).filter(|&h| h < MAX_HARTS) {
+                s_contexts[hart] = Some(context);
+            }
+        }
+        context += 1;
+    }
+
+    Some(PlicInfo {
+        region: first_region(node)?,
+        ndev,
+        s_contexts,
+    })
+}
+
+/// 解析设备树，dtb_pa 是 OpenSBI 通过 a1 寄存器传给内核的物理地址
+/// 必须在开启分页之前调用
+pub fn init(dtb_pa: usize) {
+    let fdt = unsafe { Fdt::from_ptr(dtb_pa as *const u8) }.expect("Invalid device tree blob");
+
+    let uart = fdt.find_compatible(&["ns16550a"]).and_then(|node| {
+        Some(UartInfo {
+            region: first_region(node)?,
+            irq: node.interrupts().and_then(|mut irqs| irqs.next()),
+        })
+    });
+
+    let plic = parse_plic(&fdt);
+
+    let rtc = fdt
+        .find_compatible(&["google,goldfish-rtc"])
+        .and_then(|node| {
+            Some(RtcInfo {
+                region: first_region(node)?,
+            })
+        });
+
+    // fdt.chosen() 在没有 /chosen 节点时会 panic，这里自己查找
+    let bootargs = fdt
+        .find_node("/chosen")
+        .and_then(|chosen| chosen.property("bootargs"))
+        .and_then(|p| p.as_str())
+        .unwrap_or("");
+    let bootargs = BootArgs::new(bootargs);
+
+    let timebase_freq = fdt
+        .find_node("/cpus")
+        .and_then(|cpus| cpus.property("timebase-frequency"))
+        .and_then(|p| p.as_usize())
+        .unwrap_or(DEFAULT_TIMEBASE_FREQ);
+
+    let info = BOARD_INFO.call_once(|| BoardInfo {
+        uart,
+        plic,
+        rtc,
+        bootargs,
+        timebase_freq,
+    });
+    info!("Board info: {:x?}", info);
+}
+
+pub fn info() -> &'static BoardInfo {
+    BOARD_INFO.get().expect("board::init has not been called")
+}
+
+/// time CSR 的频率，board::init 之前 (如早期日志) 使用 QEMU 的默认值
+pub fn timebase_freq() -> usize {
+    BOARD_INFO
+        .get()
+        .map_or(DEFAULT_TIMEBASE_FREQ, |info| info.timebase_freq)
+}
+
+/// 需要在内核地址空间中映射的所有 MMIO 区域
+pub fn mmio_regions() -> impl Iterator<Item = MmioRegion> {
+    let info = info();
+    let uart = info.uart.map(|uart| uart.region);
+    let plic = info.plic.map(|plic| plic.region);
+    let rtc = info.rtc.map(|rtc| rtc.region);
+    uart.into_iter().chain(plic).chain(rtc)
+}
@@ -0,0 +1,17 @@
+// 内核的全局配置常量
+
+use crate::mm::address::PAGE_SIZE;
+
+/// 支持的最大 hart 数量
+pub const MAX_HARTS: usize = 8;
+
+/// 每个用户程序的用户栈大小
+pub const USER_STACK_SIZE: usize = 4096 * 2;
+
+/// 跳板页位于地址空间的最高一页
+pub const TRAMPOLINE: usize = usize::MAX - PAGE_SIZE + 1;
+/// 陷入上下文位于跳板页下面一页
+pub const TRAP_CONTEXT: usize = TRAMPOLINE - PAGE_SIZE;
+
+/// 调度时钟的周期 (毫秒)，每个周期检查一次是否需要切换任务
+pub const SCHED_TICK_MS: u64 = 10;
@@ -1,21 +1,89 @@
-use crate::sbi::console_putchar;
+use crate::cpu;
+use crate::drivers::uart;
+use crate::kmsg;
+use crate::sbi::console_write;
 use core::fmt::{self, Write};
+use core::sync::atomic::{AtomicBool, Ordering};
+use spin::{Mutex, MutexGuard};
+
+// 控制台锁：一次 print / write 的内容整体输出，不会和其他 hart 或中断处理函数的输出交错
+static CONSOLE_LOCK: Mutex<()> = Mutex::new(());
+
+// 进入 panic 之后为 true
+static PANICKING: AtomicBool = AtomicBool::new(false);
+
+/// 持有控制台锁期间关闭中断，否则中断处理函数 (如终端回显) 输出时会在同一个 hart 上死锁
+struct ConsoleGuard {
+    guard: Option<MutexGuard<'static, ()>>,
+}
+
+fn lock() -> ConsoleGuard {
+    cpu::push_off();
+    ConsoleGuard {
+        guard: Some(CONSOLE_LOCK.lock()),
+    }
+}
+
+impl Drop for ConsoleGuard {
+    fn drop(&mut self) {
+        // 先释放锁再恢复中断
+        self.guard.take();
+        cpu::pop_off();
+    }
+}
 
 struct Stdout;
 
 impl Write for Stdout {
     fn write_str(&mut self, s: &str) -> fmt::Result {
-        for c in s.chars() {
-            console_putchar(c as usize);
-        }
+        // 按 UTF-8 字节整体输出，而不是逐个 char 截断成码点
+        write_raw(s.as_bytes());
         Ok(())
     }
 }
 
+/// 调用者需要持有控制台锁
+fn write_raw(bytes: &[u8]) {
+    // UART 驱动初始化之前 (早期启动阶段) 通过 SBI 输出
+    match uart::get() {
+        Some(uart) => uart.write_bytes(bytes),
+        None => console_write(bytes),
+    }
+}
+
+/// 输出一段字节，用户程序的 write 也走这里
+pub fn write_bytes(bytes: &[u8]) {
+    let _guard = lock();
+    write_raw(bytes);
+}
+
+/// 输出到控制台，同时记录到内核日志缓冲区
 pub fn print(args: fmt::Arguments) {
+    kmsg::log(kmsg::LOG_INFO, args);
+    print_console(args);
+}
+
+/// 只输出到控制台，不记录到内核日志缓冲区
+pub fn print_console(args: fmt::Arguments) {
+    let _guard = lock();
     Stdout.write_fmt(args).unwrap();
 }
 
+/// 进入 panic：强制释放控制台锁，保证 panic 信息一定能输出
+/// panic 可能发生在持有锁的时候 (例如格式化参数时出错)，这时正常加锁会死锁
+pub fn enter_panic() {
+    PANICKING.store(true, Ordering::SeqCst);
+    if CONSOLE_LOCK.is_locked() {
+        // 持有锁的代码不会再继续执行，输出可能会和被打断的那一行混在一起
+        unsafe { CONSOLE_LOCK.force_unlock() };
+    }
+}
+
+/// 是否已经进入 panic，其他模块据此避免在 panic 时等待可能永远不会释放的锁
+pub fn panicking() -> bool {
+    PANICKING.load(Ordering::SeqCst)
+}
+
 // 宏定义：print!
 #[macro_export]
 macro_rules! print {
@@ -30,13 +98,3 @@ macro_rules! println {
     () => ($crate::print!("\n"));
     ($($arg:tt)*) => ($crate::print!("{}\n", format_args!($($arg)*)));
 }
-
-// 新增：debug! 宏
-// 输出格式：[DEBUG] file:line message
-// 使用绿色 (\x1b[32m) 高亮 [DEBUG] 前缀
-#[macro_export]
-macro_rules! debug {
-    ($($arg:tt)*) => ({
-        $crate::print!("\x1b[32m[DEBUG] {}:{}\x1b[0m {}\n", file!(), line!(), format_args!($($arg)*));
-    });
-}
@@ -0,0 +1,90 @@
+use crate::config::MAX_HARTS;
+use core::arch::asm;
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+// sstatus.SIE: S 模式全局中断使能
+const SSTATUS_SIE: usize = 1 << 1;
+// sie.STIE: S 模式时钟中断使能
+const SIE_STIE: usize = 1 << 5;
+// sie.SEIE: S 模式外部中断使能
+const SIE_SEIE: usize = 1 << 9;
+
+/// 当前 hart 的 id，启动时由 entry.asm 保存在 tp 寄存器中
+pub fn hart_id() -> usize {
+    let id;
+    unsafe { asm!("mv {}, tp", out(reg) id) };
+    id
+}
+
+/// 等待并处理中断 (内核平时运行时 sstatus.SIE 是关闭的)
+/// 即使 SIE 关闭，wfi 也会在有中断挂起时返回；之后短暂打开 SIE，让挂起的中断进入 __kernel_trap 被处理。
+/// 这样在 "检查条件" 和 wfi 之间到来的中断也不会被错过
+pub fn wait_for_interrupt() {
+    unsafe {
+        asm!(
+            "wfi",
+            "csrs sstatus, {sie}",
+            "csrc sstatus, {sie}",
+            sie = in(reg) SSTATUS_SIE
+        );
+    }
+}
+
+/// 关闭中断，返回之前 sstatus.SIE 是否打开
+fn disable_interrupts() -> bool {
+    let sstatus: usize;
+    unsafe { asm!("csrrc {}, sstatus, {}", out(reg) sstatus, in(reg) SSTATUS_SIE) };
+    sstatus & SSTATUS_SIE != 0
+}
+
+fn interrupts_enabled() -> bool {
+    let sstatus: usize;
+    unsafe { asm!("csrr {}, sstatus", out(reg) sstatus) };
+    sstatus & SSTATUS_SIE != 0
+}
+
+/// 每个 hart 上关中断的嵌套层数，以及最外层关中断之前 sstatus.SIE 是否打开
+struct IrqState {
+    depth: AtomicUsize,
+    enabled: AtomicBool,
+}
+
+// 只有对应的 hart 自己会访问，原子类型只是为了能放在 static 中
+static IRQ_STATE: [IrqState; MAX_HARTS] = [const {
+    IrqState {
+        depth: AtomicUsize::new(0),
+        enabled: AtomicBool::new(false),
+    }
+}; MAX_HARTS];
+
+/// 关闭中断，可以嵌套，和 pop_off 成对使用
+pub fn push_off() {
+    let enabled = disable_interrupts();
+    let state = &IRQ_STATE[hart_id()];
+    if state.depth.load(Ordering::Relaxed) == 0 {
+        state.enabled.store(enabled, Ordering::Relaxed);
+    }
+    state.depth.fetch_add(1, Ordering::Relaxed);
+}
+
+/// 退出一层 push_off，最外层退出时恢复之前的中断状态
+pub fn pop_off() {
+    assert!(!interrupts_enabled(), "pop_off with interrupts enabled");
+    let state = &IRQ_STATE[hart_id()];
+    let depth = state.depth.load(Ordering::Relaxed);
+    assert!(depth > 0, "pop_off without push_off");
+    state.depth.store(depth - 1, Ordering::Relaxed);
+    if depth == 1 && state.enabled.load(Ordering::Relaxed) {
+        unsafe { asm!("csrs sstatus, {}", in(reg) SSTATUS_SIE) };
+    }
+}
+
+/// 允许 SBI 设置的时钟中断进入 S 模式
+pub fn enable_timer_interrupt() {
+    unsafe { asm!("csrs sie, {}", in(reg) SIE_STIE) };
+}
+
+/// 允许 PLIC 转发的外部中断进入 S 模式
+pub fn enable_external_interrupt() {
+    unsafe { asm!("csrs sie, {}", in(reg) SIE_SEIE) };
+}
@@ -0,0 +1,11 @@
+pub mod plic;
+pub mod rtc;
+pub mod uart;
+
+/// 初始化设备驱动，需要在内核地址空间映射好 MMIO 区域之后调用
+pub fn init() {
+    // 先初始化 PLIC，其他驱动初始化时要向它注册中断
+    plic::init();
+    uart::init();
+    rtc::init();
+}
@@ -0,0 +1,133 @@
+use crate::board;
+use crate::config::MAX_HARTS;
+use crate::cpu::hart_id;
+use alloc::collections::BTreeMap;
+use core::ptr::{read_volatile, write_volatile};
+use spin::{Mutex, Once};
+
+// PLIC 寄存器布局 (相对于基地址)
+const PRIORITY_BASE: usize = 0x0; // 每个中断源 4 字节的优先级
+const ENABLE_BASE: usize = 0x2000; // 每个 context 一张中断使能位图
+const ENABLE_STRIDE: usize = 0x80;
+const CONTEXT_BASE: usize = 0x20_0000; // 每个 context 的阈值和 claim/complete 寄存器
+const CONTEXT_STRIDE: usize = 0x1000;
+const CONTEXT_THRESHOLD: usize = 0x0;
+const CONTEXT_CLAIM: usize = 0x4; // 读: claim，写: complete
+
+pub struct Plic {
+    base: usize,
+    ndev: usize,
+}
+
+impl Plic {
+    fn read_reg(&self, offset: usize) -> u32 {
+        unsafe { read_volatile((self.base + offset) as *const u32) }
+    }
+
+    fn write_reg(&self, offset: usize, val: u32) {
+        unsafe { write_volatile((self.base + offset) as *mut u32, val) }
+    }
+
+    /// 设置中断源的优先级，优先级为 0 的中断永远不会被送达
+    pub fn set_priority(&self, irq: usize, priority: u32) {
+        self.write_reg(PRIORITY_BASE + irq * 4, priority);
+    }
+
+    /// 在 context 上打开中断源 irq
+    pub fn enable(&self, context: usize, irq: usize) {
+        let offset = ENABLE_BASE + context * ENABLE_STRIDE + (irq / 32) * 4;
+        let bits = self.read_reg(offset);
+        self.write_reg(offset, bits | (1 << (irq % 32)));
+    }
+
+    /// 设置 context 的优先级阈值，只有优先级大于阈值的中断才会被送达
+    pub fn set_threshold(&self, context: usize, threshold: u32) {
+        self.write_reg(
+            CONTEXT_BASE + context * CONTEXT_STRIDE + CONTEXT_THRESHOLD,
+            threshold,
+        );
+    }
+
+    /// 领取一个挂起的中断，返回 0 表示没有中断
+    pub fn claim(&self, context: usize) -> usize {
+        self.read_reg(CONTEXT_BASE + context * CONTEXT_STRIDE + CONTEXT_CLAIM) as usize
+    }
+
+    /// 通知 PLIC 中断 irq 已经处理完毕
+    pub fn complete(&self, context: usize, irq: usize) {
+        self.write_reg(
+            CONTEXT_BASE + context * CONTEXT_STRIDE + CONTEXT_CLAIM,
+            irq as u32,
+        );
+    }
+}
+
+static PLIC: Once<Plic> = Once::new();
+
+// 中断号 -> 中断处理函数
+static IRQ_HANDLERS: Mutex<BTreeMap<usize, fn()>> = Mutex::new(BTreeMap::new());
+
+/// 当前 hart 在 S 模式下对应的 PLIC context
+fn current_context() -> Option<usize> {
+    let hart = hart_id();
+    if hart >= MAX_HARTS {
+        return None;
+    }
+    board::info().plic?.s_contexts[hart]
+}
+
+pub fn init() {
+    let Some(info) = board::info().plic else {
+        warn!("no PLIC found, external interrupts disabled");
+        return;
+    };
+    let plic = PLIC.call_once(|| Plic {
+        base: info.region.base,
+        ndev: info.ndev,
+    });
+    // 阈值为 0：所有优先级大于 0 的中断都会送到当前 hart
+    if let Some(context) = current_context() {
+        plic.set_threshold(context, 0);
+    }
+    info!(
+        "PLIC at {:#x}, {} interrupt sources",
+        info.region.base, info.ndev
+    );
+}
+
+/// 注册中断处理函数，并在当前 hart 上打开中断源 irq
+pub fn register_irq(irq: usize, handler: fn()) {
+    let (Some(plic), Some(context)) = (PLIC.get(), current_context()) else {
+        warn!("cannot register irq {}: no PLIC context", irq);
+        return;
+    };
+    assert!(
+        irq > 0 && irq <= plic.ndev,
+        "irq {} out of range 1..={}",
+        irq,
+        plic.ndev
+    );
+    IRQ_HANDLERS.lock().insert(irq, handler);
+    plic.set_priority(irq, 1);
+    plic.enable(context, irq);
+}
+
+/// S 模式外部中断的处理入口：依次领取并分发所有挂起的中断
+pub fn handle_irq() {
+    let (Some(plic), Some(context)) = (PLIC.get(), current_context()) else {
+        return;
+    };
+    loop {
+        let irq = plic.claim(context);
+        if irq == 0 {
+            break;
+        }
+        // 先取出处理函数再调用，避免在持有锁的情况下执行驱动代码
+        let handler = IRQ_HANDLERS.lock().get(&irq).copied();
+        match handler {
+            Some(handler) => handler(),
+            None => warn!("unexpected irq {}", irq),
+        }
+        plic.complete(context, irq);
+    }
+}
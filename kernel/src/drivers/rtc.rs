@@ -0,0 +1,71 @@
+use crate::board;
+use crate::timer;
+use core::ptr::{read_volatile, write_volatile};
+use spin::Once;
+
+// Goldfish RTC 寄存器偏移，时间是 UNIX 时间 (纳秒)
+const TIME_LOW: usize = 0x00; // 读低 32 位时锁存高 32 位
+const TIME_HIGH: usize = 0x04;
+const IRQ_ENABLED: usize = 0x10; // 闹钟中断使能
+const CLEAR_ALARM: usize = 0x14;
+const CLEAR_INTERRUPT: usize = 0x1c;
+
+pub struct GoldfishRtc {
+    base: usize,
+}
+
+impl GoldfishRtc {
+    fn read_reg(&self, offset: usize) -> u32 {
+        unsafe { read_volatile((self.base + offset) as *const u32) }
+    }
+
+    fn write_reg(&self, offset: usize, val: u32) {
+        unsafe { write_volatile((self.base + offset) as *mut u32, val) }
+    }
+
+    /// 当前的 UNIX 时间 (纳秒)
+    pub fn read_time_ns(&self) -> u64 {
+        // 必须先读低 32 位，高 32 位是读低位时锁存的值
+        let low = self.read_reg(TIME_LOW) as u64;
+        let high = self.read_reg(TIME_HIGH) as u64;
+        high << 32 | low
+    }
+
+    /// 设置 UNIX 时间 (纳秒)
+    pub fn set_time_ns(&self, ns: u64) {
+        // 先写高 32 位，写低 32 位时才真正生效
+        self.write_reg(TIME_HIGH, (ns >> 32) as u32);
+        self.write_reg(TIME_LOW, ns as u32);
+    }
+}
+
+static RTC: Once<GoldfishRtc> = Once::new();
+
+/// 读取 RTC 作为开机时的实时时钟
+pub fn init() {
+    let Some(info) = board::info().rtc else {
+        warn!("no goldfish rtc found, realtime clock starts at the epoch");
+        return;
+    };
+    let rtc = RTC.call_once(|| GoldfishRtc {
+        base: info.region.base,
+    });
+    // 内核不使用闹钟，关掉可能残留的闹钟中断
+    rtc.write_reg(IRQ_ENABLED, 0);
+    rtc.write_reg(CLEAR_ALARM, 1);
+    rtc.write_reg(CLEAR_INTERRUPT, 1);
+
+    let now = rtc.read_time_ns();
+    timer::set_realtime_ns(now);
+    info!(
+        "goldfish rtc at {:#x}, epoch time {}.{:09}",
+        info.region.base,
+        now / timer::NSEC_PER_SEC,
+        now % timer::NSEC_PER_SEC
+    );
+}
+
+/// RTC 驱动初始化之后才返回 Some
+pub fn get() -> Option<&'static GoldfishRtc> {
+    RTC.get()
+}
@@ -0,0 +1,123 @@
+use crate::board;
+use crate::drivers::plic;
+use crate::tty;
+use core::ptr::{read_volatile, write_volatile};
+use spin::Once;
+
+// NS16550A 寄存器偏移 (QEMU virt 上 reg-shift 为 0，每个寄存器占 1 字节)
+const RBR: usize = 0; // 接收缓冲寄存器 (读)
+const THR: usize = 0; // 发送保持寄存器 (写)
+const IER: usize = 1; // 中断使能寄存器
+const FCR: usize = 2; // FIFO 控制寄存器 (写)
+const LCR: usize = 3; // 线路控制寄存器
+const MCR: usize = 4; // Modem 控制寄存器
+const LSR: usize = 5; // 线路状态寄存器
+
+const IER_RX_AVAILABLE: u8 = 1 << 0; // 接收到数据时产生中断
+
+const FCR_ENABLE: u8 = 1 << 0; // 打开收发 FIFO
+const FCR_CLEAR_RX: u8 = 1 << 1; // 清空接收 FIFO
+const FCR_CLEAR_TX: u8 = 1 << 2; // 清空发送 FIFO
+
+const LCR_WORD_8BIT: u8 = 0b11; // 8 位数据位，无校验，1 位停止位
+
+const MCR_DTR: u8 = 1 << 0;
+const MCR_RTS: u8 = 1 << 1;
+const MCR_OUT2: u8 = 1 << 3; // 把中断信号输出到中断控制器
+
+const LSR_DATA_READY: u8 = 1 << 0; // 接收 FIFO 中有数据
+const LSR_THR_EMPTY: u8 = 1 << 5; // 发送 FIFO 已空
+
+// 16550 收发 FIFO 的深度
+const FIFO_SIZE: usize = 16;
+
+pub struct Ns16550a {
+    base: usize,
+}
+
+impl Ns16550a {
+    fn read_reg(&self, offset: usize) -> u8 {
+        unsafe { read_volatile((self.base + offset) as *const u8) }
+    }
+
+    fn write_reg(&self, offset: usize, val: u8) {
+        unsafe { write_volatile((self.base + offset) as *mut u8, val) }
+    }
+
+    fn init(&self) {
+        // 初始化期间先关闭所有中断
+        self.write_reg(IER, 0);
+        // QEMU 不关心波特率，这里不设置除数寄存器
+        self.write_reg(LCR, LCR_WORD_8BIT);
+        // 打开并清空收发 FIFO，接收 FIFO 中有 1 个字节就触发中断
+        self.write_reg(FCR, FCR_ENABLE | FCR_CLEAR_RX | FCR_CLEAR_TX);
+        self.write_reg(MCR, MCR_DTR | MCR_RTS | MCR_OUT2);
+        // 打开接收中断，收到数据时经 PLIC 通知 CPU
+        self.write_reg(IER, IER_RX_AVAILABLE);
+    }
+
+    /// 发送一个字节，space 记录发送 FIFO 中还能放下多少字节
+    fn push_tx(&self, byte: u8, space: &mut usize) {
+        // 等发送 FIFO 清空后，可以一口气写入 FIFO_SIZE 个字节，不用每个字节都查询 LSR
+        if *space == 0 {
+            while self.read_reg(LSR) & LSR_THR_EMPTY == 0 {
+                core::hint::spin_loop();
+            }
+            *space = FIFO_SIZE;
+        }
+        self.write_reg(THR, byte);
+        *space -= 1;
+    }
+
+    /// 发送一段字节，和 OpenSBI 的控制台一样把 '\n' 扩展成 "\r\n"
+    pub fn write_bytes(&self, bytes: &[u8]) {
+        let mut space = 0;
+        for &b in bytes {
+            if b == b'\n' {
+                self.push_tx(b'\r', &mut space);
+            }
+            self.push_tx(b, &mut space);
+        }
+    }
+
+    /// 从接收 FIFO 中读取一个字节，FIFO 为空时返回 None
+    pub fn read_byte(&self) -> Option<u8> {
+        if self.read_reg(LSR) & LSR_DATA_READY != 0 {
+            Some(self.read_reg(RBR))
+        } else {
+            None
+        }
+    }
+}
+
+static UART: Once<Ns16550a> = Once::new();
+
+pub fn init() {
+    let Some(info) = board::info().uart else {
+        warn!("no ns16550a found, keep using SBI console");
+        return;
+    };
+    let uart = UART.call_once(|| Ns16550a {
+        base: info.region.base,
+    });
+    uart.init();
+    if let Some(irq) = info.irq {
+        plic::register_irq(irq, handle_irq);
+    }
+    info!("ns16550a at {:#x}", info.region.base);
+}
+
+/// UART 驱动初始化之后才返回 Some
+pub fn get() -> Option<&'static Ns16550a> {
+    UART.get()
+}
+
+/// 接收中断处理：把 RX FIFO 中的数据全部交给终端的行规程
+pub fn handle_irq() {
+    let Some(uart) = get() else {
+        return;
+    };
+    while let Some(byte) = uart.read_byte() {
+        tty::receive(byte);
+    }
+}
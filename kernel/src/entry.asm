@@ -6,6 +6,8 @@ _start:
 # 1. 设置栈指针
 # la 是 load address，将 boot_stack_top 的地址加载到 sp 寄存器
     la       sp, boot_stack_top
+# OpenSBI 把当前 hart 的 id 放在 a0 中，保存到 tp 寄存器供内核随时读取
+    mv       tp, a0
 
 # 2. 清零 .bss 段 (新增步骤)
     la       t0, sbss
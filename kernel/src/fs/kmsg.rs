@@ -0,0 +1,70 @@
+use super::File;
+use crate::kmsg;
+use crate::syscall::errno::{EINVAL, EPIPE};
+use alloc::format;
+use alloc::string::String;
+use spin::Mutex;
+
+/// /dev/kmsg：每次 read 返回一条内核日志，格式与 Linux 相同
+/// "级别,序号,时间戳(微秒),-;消息\n"
+/// 和 Linux 不同的是，读完所有消息后 read 返回 0 而不是阻塞
+pub struct KmsgFile {
+    readable: bool,
+    writable: bool,
+    seq: Mutex<u64>, // 下一条要读取的消息的序号
+}
+
+impl KmsgFile {
+    pub fn new(readable: bool, writable: bool) -> Self {
+        Self {
+            readable,
+            writable,
+            seq: Mutex::new(0),
+        }
+    }
+}
+
+impl File for KmsgFile {
+    fn readable(&self) -> bool {
+        self.readable
+    }
+
+    fn writable(&self) -> bool {
+        self.writable
+    }
+
+    fn read(&self, buf: &mut [u8]) -> isize {
+        let mut seq = self.seq.lock();
+        let Some(record) = kmsg::read(*seq) else {
+            return 0;
+        };
+        // 要读的消息已经被覆盖，跳到现存最早的消息并报告一次 EPIPE
+        if record.seq != *seq {
+            *seq = record.seq;
+            return -EPIPE;
+        }
+        let line = format!(
+            "{},{},{},-;{}\n",
+            record.level,
+            record.seq,
+            record.timestamp_us,
+            String::from_utf8_lossy(&record.text)
+        );
+        // 缓冲区放不下一整条消息
+        if line.len() > buf.len() {
+            return -EINVAL;
+        }
+        buf[..line.len()].copy_from_slice(line.as_bytes());
+        *seq += 1;
+        line.len() as isize
+    }
+
+    /// 用户程序写入的内容作为一条内核日志
+    fn write(&self, buf: &[u8]) -> isize {
+        kmsg::log(
+            kmsg::LOG_INFO,
+            format_args!("{}", String::from_utf8_lossy(buf)),
+        );
+        buf.len() as isize
+    }
+}
@@ -0,0 +1,40 @@
+mod kmsg;
+mod stdio;
+
+use alloc::sync::Arc;
+pub use kmsg::KmsgFile;
+pub use stdio::{Stdin, Stdout};
+
+/// 打开的文件：任务通过文件描述符访问的对象
+pub trait File: Send + Sync {
+    fn readable(&self) -> bool;
+    fn writable(&self) -> bool;
+    /// 读取到内核缓冲区 buf 中，返回读到的字节数，出错时返回负的错误码
+    fn read(&self, buf: &mut [u8]) -> isize;
+    /// 写入 buf 中的数据，返回写入的字节数，出错时返回负的错误码
+    fn write(&self, buf: &[u8]) -> isize;
+    /// 终端才支持 termios 相关的 ioctl
+    fn is_tty(&self) -> bool {
+        false
+    }
+}
+
+// open 的访问模式，取值与 Linux 相同
+pub const O_RDONLY: u32 = 0;
+pub const O_WRONLY: u32 = 1;
+pub const O_RDWR: u32 = 2;
+const O_ACCMODE: u32 = 3;
+
+/// 按路径打开文件，目前只有几个设备文件
+pub fn open(path: &str, flags: u32) -> Option<Arc<dyn File>> {
+    let (readable, writable) = match flags & O_ACCMODE {
+        O_RDONLY => (true, false),
+        O_WRONLY => (false, true),
+        O_RDWR => (true, true),
+        _ => return None,
+    };
+    match path {
+        "/dev/kmsg" => Some(Arc::new(KmsgFile::new(readable, writable))),
+        _ => None,
+    }
+}
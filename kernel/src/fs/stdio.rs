@@ -0,0 +1,60 @@
+use super::File;
+use crate::console;
+use crate::syscall::errno::EINTR;
+use crate::tty;
+
+/// 标准输入：从终端读取
+pub struct Stdin;
+
+/// 标准输出 / 标准错误：输出到控制台
+pub struct Stdout;
+
+impl File for Stdin {
+    fn readable(&self) -> bool {
+        true
+    }
+
+    fn writable(&self) -> bool {
+        false
+    }
+
+    /// 没有输入时阻塞直到用户按下回车 (原始模式下是任意键)
+    fn read(&self, buf: &mut [u8]) -> isize {
+        let Some(data) = tty::read(buf.len()) else {
+            return -EINTR;
+        };
+        buf[..data.len()].copy_from_slice(&data);
+        data.len() as isize
+    }
+
+    fn write(&self, _buf: &[u8]) -> isize {
+        panic!("Cannot write to stdin!");
+    }
+
+    fn is_tty(&self) -> bool {
+        true
+    }
+}
+
+impl File for Stdout {
+    fn readable(&self) -> bool {
+        false
+    }
+
+    fn writable(&self) -> bool {
+        true
+    }
+
+    fn read(&self, _buf: &mut [u8]) -> isize {
+        panic!("Cannot read from stdout!");
+    }
+
+    fn write(&self, buf: &[u8]) -> isize {
+        console::write_bytes(buf);
+        buf.len() as isize
+    }
+
+    fn is_tty(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,175 @@
+use crate::timer;
+use alloc::vec::Vec;
+use core::fmt::{self, Write};
+use spin::Mutex;
+
+// 内核日志缓冲区 (dmesg)：内核输出的每一条消息都会追加到这里，带有序号、级别和时间戳
+// 缓冲区满了之后覆盖最早的消息；任务崩溃之后仍然可以通过 syslog 系统调用或 /dev/kmsg 读取
+
+const KMSG_BUFFER_SIZE: usize = 16 * 1024;
+// 单条消息的最大长度，超出的部分被截断
+const MAX_MESSAGE_LEN: usize = 512;
+// 每条消息的头部：序号 (8) + 时间戳 (8) + 级别 (1) + 消息长度 (2)
+const HEADER_SIZE: usize = 19;
+
+// 消息的级别，取值与 Linux 的 syslog 相同
+pub const LOG_ERR: u8 = 3;
+pub const LOG_WARNING: u8 = 4;
+pub const LOG_INFO: u8 = 6;
+pub const LOG_DEBUG: u8 = 7;
+
+/// 从缓冲区中读出的一条消息
+pub struct Record {
+    pub seq: u64,
+    pub timestamp_us: u64,
+    pub level: u8,
+    pub text: Vec<u8>,
+}
+
+/// 按消息存放的环形缓冲区，start 和 end 只增不减，取模后才是数组下标
+struct LogBuffer {
+    buf: [u8; KMSG_BUFFER_SIZE],
+    start: usize, // 最早一条消息的位置
+    end: usize,   // 下一条消息写入的位置
+    first_seq: u64,
+    next_seq: u64,
+    clear_seq: u64, // syslog 的 "清空" 只是跳过这之前的消息，/dev/kmsg 仍然能读到
+}
+
+static LOG_BUFFER: Mutex<LogBuffer> = Mutex::new(LogBuffer {
+    buf: [0; KMSG_BUFFER_SIZE],
+    start: 0,
+    end: 0,
+    first_seq: 0,
+    next_seq: 0,
+    clear_seq: 0,
+});
+
+impl LogBuffer {
+    fn write_bytes(&mut self, pos: usize, bytes: &[u8]) {
+        for (i, &b) in bytes.iter().enumerate() {
+            self.buf[(pos + i) % KMSG_BUFFER_SIZE] = b;
+        }
+    }
+
+    fn read_bytes(&self, pos: usize, bytes: &mut [u8]) {
+        for (i, b) in bytes.iter_mut().enumerate() {
+            *b = self.buf[(pos + i) % KMSG_BUFFER_SIZE];
+        }
+    }
+
+    /// 位于 pos 的消息的总长度 (包括头部)
+    fn record_size(&self, pos: usize) -> usize {
+        let mut len = [0; 2];
+        self.read_bytes(pos + 17, &mut len);
+        HEADER_SIZE + u16::from_le_bytes(len) as usize
+    }
+
+    /// 丢弃最早的一条消息
+    fn drop_oldest(&mut self) {
+        self.start += self.record_size(self.start);
+        self.first_seq += 1;
+    }
+
+    fn push(&mut self, level: u8, text: &[u8]) {
+        let size = HEADER_SIZE + text.len();
+        while KMSG_BUFFER_SIZE - (self.end - self.start) < size {
+            self.drop_oldest();
+        }
+        let timestamp_us = timer::monotonic_us();
+        let pos = self.end;
+        self.write_bytes(pos, &self.next_seq.to_le_bytes());
+        self.write_bytes(pos + 8, &timestamp_us.to_le_bytes());
+        self.write_bytes(pos + 16, &[level]);
+        self.write_bytes(pos + 17, &(text.len() as u16).to_le_bytes());
+        self.write_bytes(pos + HEADER_SIZE, text);
+        self.end += size;
+        self.next_seq += 1;
+    }
+
+    fn read_record(&self, pos: usize) -> Record {
+        let mut header = [0; HEADER_SIZE];
+        self.read_bytes(pos, &mut header);
+        let mut text = alloc::vec![0; self.record_size(pos) - HEADER_SIZE];
+        self.read_bytes(pos + HEADER_SIZE, &mut text);
+        Record {
+            seq: u64::from_le_bytes(header[0..8].try_into().unwrap()),
+            timestamp_us: u64::from_le_bytes(header[8..16].try_into().unwrap()),
+            level: header[16],
+            text,
+        }
+    }
+
+    /// 序号不小于 seq 的第一条消息，seq 已被覆盖时返回现存最早的一条
+    fn find(&self, seq: u64) -> Option<Record> {
+        if seq >= self.next_seq {
+            return None;
+        }
+        let mut pos = self.start;
+        for _ in self.first_seq..seq.max(self.first_seq) {
+            pos += self.record_size(pos);
+        }
+        Some(self.read_record(pos))
+    }
+}
+
+/// 把格式化的消息写入定长的数组，超出的部分被截断
+struct MessageWriter {
+    buf: [u8; MAX_MESSAGE_LEN],
+    len: usize,
+}
+
+impl Write for MessageWriter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let n = s.len().min(MAX_MESSAGE_LEN - self.len);
+        self.buf[self.len..self.len + n].copy_from_slice(&s.as_bytes()[..n]);
+        self.len += n;
+        Ok(())
+    }
+}
+
+/// 追加一条消息，末尾的换行符不保存
+/// 不使用堆，在堆初始化之前也可以调用
+pub fn log(level: u8, args: fmt::Arguments) {
+    let mut message = MessageWriter {
+        buf: [0; MAX_MESSAGE_LEN],
+        len: 0,
+    };
+    let _ = message.write_fmt(args);
+    let text = &message.buf[..message.len];
+    let text = text.strip_suffix(b"\n").unwrap_or(text);
+    if text.is_empty() {
+        return;
+    }
+    // panic 时缓冲区的锁可能正被打断的代码持有，拿不到锁就只输出到控制台
+    let buffer = if crate::console::panicking() {
+        LOG_BUFFER.try_lock()
+    } else {
+        Some(LOG_BUFFER.lock())
+    };
+    if let Some(mut buffer) = buffer {
+        buffer.push(level, text);
+    }
+}
+
+/// 读取序号不小于 seq 的第一条消息，没有更新的消息时返回 None
+/// 返回的序号比 seq 大，说明中间的消息已经被覆盖了
+pub fn read(seq: u64) -> Option<Record> {
+    LOG_BUFFER.lock().find(seq)
+}
+
+/// syslog 读取的起点：最早一条未被清空的消息
+pub fn clear_seq() -> u64 {
+    LOG_BUFFER.lock().clear_seq
+}
+
+/// syslog 的清空：之后的读取跳过现有的消息
+pub fn clear() {
+    let mut buffer = LOG_BUFFER.lock();
+    buffer.clear_seq = buffer.next_seq;
+}
+
+/// 缓冲区的总大小
+pub fn buffer_size() -> usize {
+    KMSG_BUFFER_SIZE
+}
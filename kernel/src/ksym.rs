@@ -0,0 +1,78 @@
+// 内核函数符号表：链接脚本在 .ksymtab 段预留空间，构建之后由 tools/ksym 把 ELF 中的函数符号写进去
+// 格式见 tools/ksym/src/main.rs；没有运行 tools/ksym 时段内全是 0，栈回溯只能打印地址
+
+const MAGIC: &[u8; 4] = b"KSYM";
+const HEADER_SIZE: usize = 8;
+const ENTRY_SIZE: usize = 24;
+
+extern "C" {
+    static sksymtab: u8;
+    static eksymtab: u8;
+}
+
+fn table() -> &'static [u8] {
+    unsafe {
+        let start = &sksymtab as *const u8;
+        let end = &eksymtab as *const u8;
+        core::slice::from_raw_parts(start, end as usize - start as usize)
+    }
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> usize {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap()) as usize
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> usize {
+    u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap()) as usize
+}
+
+/// 一个函数符号
+struct Symbol {
+    addr: usize,
+    size: usize,
+    name_off: usize,
+    name_len: usize,
+}
+
+fn symbol(table: &[u8], index: usize) -> Symbol {
+    let entry = HEADER_SIZE + index * ENTRY_SIZE;
+    Symbol {
+        addr: read_u64(table, entry),
+        size: read_u64(table, entry + 8),
+        name_off: read_u32(table, entry + 16),
+        name_len: read_u32(table, entry + 20),
+    }
+}
+
+/// 符号表是否已经写入内核
+pub fn available() -> bool {
+    &table()[..4] == MAGIC
+}
+
+/// 查找包含地址 addr 的函数，返回函数名和 addr 相对函数开头的偏移
+pub fn lookup(addr: usize) -> Option<(&'static str, usize)> {
+    if !available() {
+        return None;
+    }
+    let table = table();
+    let count = read_u32(table, 4);
+    let strings = &table[HEADER_SIZE + count * ENTRY_SIZE..];
+
+    // 符号按地址升序排列，二分查找最后一个起始地址不大于 addr 的函数
+    let mut lo = 0;
+    let mut hi = count;
+    while lo < hi {
+        let mid = (lo + hi) / 2;
+        if symbol(table, mid).addr <= addr {
+            lo = mid + 1;
+        } else {
+            hi = mid;
+        }
+    }
+    let sym = symbol(table, lo.checked_sub(1)?);
+    if sym.size != 0 && addr >= sym.addr + sym.size {
+        return None;
+    }
+    let name = core::str::from_utf8(&strings[sym.name_off..sym.name_off + sym.name_len]).ok()?;
+    Some((name, addr - sym.addr))
+}
@@ -0,0 +1,48 @@
+use core::arch::global_asm;
+
+// 用户程序由 build.rs 编译，并通过生成的 link_app.S 嵌入内核的 .data 段
+global_asm!(include_str!(concat!(env!("OUT_DIR"), "/link_app.S")));
+
+extern "C" {
+    fn _num_app();
+    fn _app_names();
+}
+
+/// 内核中嵌入的用户程序数量
+pub fn get_num_app() -> usize {
+    unsafe { (_num_app as *const () as *const usize).read_volatile() }
+}
+
+/// 第 app_id 个用户程序的 ELF 数据
+pub fn get_app_data(app_id: usize) -> &'static [u8] {
+    let num_app = get_num_app();
+    assert!(app_id < num_app);
+    // _num_app 之后是 num_app + 1 个地址：每个应用的起始地址和最后一个应用的结束地址
+    let app_start = unsafe {
+        core::slice::from_raw_parts((_num_app as *const () as *const usize).add(1), num_app + 1)
+    };
+    unsafe {
+        core::slice::from_raw_parts(
+            app_start[app_id] as *const u8,
+            app_start[app_id + 1] - app_start[app_id],
+        )
+    }
+}
+
+/// 第 app_id 个用户程序的名字 (src/bin 下的文件名)
+pub fn get_app_name(app_id: usize) -> &'static str {
+    assert!(app_id < get_num_app());
+    // _app_names 中依次存放着以 '\0' 结尾的名字
+    let mut ptr = _app_names as *const () as *const u8;
+    for _ in 0..app_id {
+        unsafe {
+            while ptr.read_volatile() != 0 {
+                ptr = ptr.add(1);
+            }
+            ptr = ptr.add(1);
+        }
+    }
+    unsafe { core::ffi::CStr::from_ptr(ptr as *const core::ffi::c_char) }
+        .to_str()
+        .unwrap()
+}
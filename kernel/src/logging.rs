@@ -0,0 +1,177 @@
+use crate::console;
+use crate::cpu;
+use crate::kmsg;
+use crate::timer;
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use spin::Once;
+
+// 内核日志：error!/warn!/info!/debug!/trace! 由 log 库提供，这里负责过滤和输出
+// 每条日志带有时间戳 (time CSR)、级别和 hart id
+// 可以在内核命令行中按模块设置级别，例如 log=warn,mm=debug,drivers::uart=trace
+// 不带模块名的一项是默认级别；模块名是去掉 "kernel::" 前缀的模块路径，子模块继承父模块的设置
+
+const DEFAULT_LEVEL: LevelFilter = LevelFilter::Info;
+// 命令行中最多能设置多少个模块
+const MAX_DIRECTIVES: usize = 16;
+
+#[derive(Debug, Clone, Copy)]
+struct Directive {
+    module: &'static str,
+    level: LevelFilter,
+}
+
+struct Filter {
+    default: LevelFilter,
+    directives: [Option<Directive>; MAX_DIRECTIVES],
+}
+
+// 解析命令行之前使用 DEFAULT_LEVEL
+static FILTER: Once<Filter> = Once::new();
+
+/// 解析 "level" 或 "module=level"，模块名为 None 表示默认级别
+fn parse_directive(item: &str) -> Option<(Option<&str>, LevelFilter)> {
+    match item.split_once('=') {
+        Some((module, level)) => Some((Some(module), level.parse().ok()?)),
+        None => Some((None, item.parse().ok()?)),
+    }
+}
+
+impl Filter {
+    fn parse(spec: &'static str) -> Self {
+        let mut filter = Filter {
+            default: DEFAULT_LEVEL,
+            directives: [None; MAX_DIRECTIVES],
+        };
+        let mut slots = filter.directives.iter_mut();
+        for item in spec.split(',') {
+            match parse_directive(item) {
+                Some((None, level)) => filter.default = level,
+                Some((Some(module), level)) => {
+                    if let Some(slot) = slots.next() {
+                        *slot = Some(Directive { module, level });
+                    }
+                }
+                None => {}
+            }
+        }
+        filter
+    }
+
+    /// 模块路径 target 的日志级别，匹配最长的那个模块名
+    fn level_for(&self, target: &str) -> LevelFilter {
+        let path = target.strip_prefix("kernel::").unwrap_or(target);
+        self.directives
+            .iter()
+            .flatten()
+            .filter(|d| {
+                path.strip_prefix(d.module)
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+            })
+            .max_by_key(|d| d.module.len())
+            .map_or(self.default, |d| d.level)
+    }
+
+    /// 所有模块中最详细的级别，log 库用它快速丢弃不需要的日志
+    fn max_level(&self) -> LevelFilter {
+        self.directives
+            .iter()
+            .flatten()
+            .map(|d| d.level)
+            .fold(self.default, Ord::max)
+    }
+}
+
+struct KernelLogger;
+
+// 对应的 syslog 级别
+fn syslog_level(level: Level) -> u8 {
+    match level {
+        Level::Error => kmsg::LOG_ERR,
+        Level::Warn => kmsg::LOG_WARNING,
+        Level::Info => kmsg::LOG_INFO,
+        Level::Debug | Level::Trace => kmsg::LOG_DEBUG,
+    }
+}
+
+// ANSI 转义序列中的颜色
+fn level_color(level: Level) -> u8 {
+    match level {
+        Level::Error => 31, // 红色
+        Level::Warn => 93,  // 亮黄色
+        Level::Info => 34,  // 蓝色
+        Level::Debug => 32, // 绿色
+        Level::Trace => 90, // 灰色
+    }
+}
+
+impl Log for KernelLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        let level = FILTER
+            .get()
+            .map_or(DEFAULT_LEVEL, |filter| filter.level_for(metadata.target()));
+        metadata.level() <= level
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let time_us = timer::monotonic_us();
+        let target = record.target();
+        let module = target.strip_prefix("kernel::").unwrap_or(target);
+        // 日志缓冲区自己记录时间戳和级别
+        kmsg::log(
+            syslog_level(record.level()),
+            format_args!("{}: {}", module, record.args()),
+        );
+        console::print_console(format_args!(
+            "[{:>5}.{:06}] \x1b[{}m{:<5}\x1b[0m [hart {}] {}: {}\n",
+            time_us / 1_000_000,
+            time_us % 1_000_000,
+            level_color(record.level()),
+            record.level(),
+            cpu::hart_id(),
+            module,
+            record.args()
+        ));
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGGER: KernelLogger = KernelLogger;
+
+/// 安装日志输出，此时还没有读取命令行，使用默认级别
+pub fn init() {
+    log::set_logger(&LOGGER).expect("logger already set");
+    log::set_max_level(DEFAULT_LEVEL);
+}
+
+/// 从内核命令行中读取 log=... 设置各模块的日志级别
+pub fn configure(cmdline: &'static str) {
+    let Some(spec) = cmdline
+        .split_whitespace()
+        .find_map(|arg| arg.strip_prefix("log="))
+    else {
+        return;
+    };
+    let filter = FILTER.call_once(|| Filter::parse(spec));
+    log::set_max_level(filter.max_level());
+
+    for item in spec.split(',') {
+        if parse_directive(item).is_none() {
+            warn!("ignoring invalid log directive {:?}", item);
+        }
+    }
+    let modules = spec
+        .split(',')
+        .filter_map(parse_directive)
+        .filter(|(module, _)| module.is_some())
+        .count();
+    if modules > MAX_DIRECTIVES {
+        warn!(
+            "too many log directives, only the first {} are used",
+            MAX_DIRECTIVES
+        );
+    }
+}
@@ -5,8 +5,23 @@
 mod sbi;
 #[macro_use] // 导出 console 模块中的宏 (println!, print!)
 mod console;
+mod backtrace;
+mod board;
+mod config;
+mod cpu;
+mod drivers;
+mod fs;
+mod kmsg;
+mod ksym;
+mod loader;
+mod logging;
 mod mm;
+mod sync;
+mod syscall;
 mod task;
+mod timer;
+mod trap;
+mod tty;
 
 use core::arch::global_asm;
 use core::panic::PanicInfo;
@@ -14,6 +29,8 @@ use core::panic::PanicInfo;
 use task::manager;
 
 extern crate alloc;
+#[macro_use] // 日志宏 error!, warn!, info!, debug!, trace!
+extern crate log;
 use alloc::{boxed::Box, vec::Vec};
 
 // 引入汇编代码
@@ -23,6 +40,10 @@ global_asm!(include_str!("entry.asm"));
 // 这个函数在 panic 发生时被调用
 #[panic_handler]
 fn panic(info: &PanicInfo) -> ! {
+    // 打印调用栈时又 panic 了，就不再回溯，避免无限递归
+    let nested = console::panicking();
+    // 保证下面的输出不会因为控制台锁而卡住
+    console::enter_panic();
     // 现在 panic 时我们可以打印错误信息了！
     if let Some(location) = info.location() {
         println!(
@@ -34,6 +55,10 @@ fn panic(info: &PanicInfo) -> ! {
     } else {
         println!("Panicked: {}", info.message());
     }
+    if !nested {
+        backtrace::backtrace();
+        trap::dump_address_space();
+    }
     loop {}
 }
 
@@ -41,8 +66,10 @@ fn panic(info: &PanicInfo) -> ! {
 // 因为 entry.asm 中的 _start 已经标记为 .text.entry 了
 // 也不需要 #[no_mangle] 了，因为我们在汇编里是用 rust_main 调用的
 // 但是为了保险起见，避免编译器混淆名字，我们还是保留 no_mangle
+// OpenSBI 跳转到内核时，a0 是当前 hart 的 id，a1 是设备树 (DTB) 的物理地址
+// entry.asm 没有修改这两个寄存器，所以它们原样成为 rust_main 的参数
 #[no_mangle]
-pub extern "C" fn rust_main() -> ! {
+pub extern "C" fn rust_main(_hart_id: usize, dtb_pa: usize) -> ! {
     // 清屏 (ANSI 转义序列)
     print!("\x1b[2J");
     // 光标移动到左上角
@@ -51,29 +78,47 @@ pub extern "C" fn rust_main() -> ! {
     println!("Hello, World!");
     println!("I am a Rust OS Kernel running on RISC-V!");
 
+    logging::init();
+
+    // 设置陷入入口，之后内核中的异常都能被捕获
+    trap::init();
+
+    // 解析设备树，找到各个设备的地址
+    board::init(dtb_pa);
+    // 命令行中的 log=... 设置日志级别
+    logging::configure(board::info().bootargs.as_str());
+
     // --- 内存分配
     mm::init();
-    println!("end mm init");
+    info!("end mm init");
+
+    // 分页已开启且 MMIO 已映射，可以切换到 UART 驱动了
+    drivers::init();
+
+    // 打开外部中断源，之后 UART 收到的数据会经 PLIC 送到陷入处理函数
+    // 内核自身运行时 sstatus.SIE 保持关闭，中断只在用户态或空闲等待时到来
+    cpu::enable_external_interrupt();
+    timer::init();
     // 测试内存分配
     let frame1 = mm::frame_allocator::alloc_frame();
     let frame2 = mm::frame_allocator::alloc_frame();
-    println!("Allocated frame 1: {:?}", frame1);
-    println!("Allocated frame 2: {:?}", frame2);
+    debug!("Allocated frame 1: {:?}", frame1);
+    debug!("Allocated frame 2: {:?}", frame2);
 
     if let Some(f) = frame1 {
         mm::frame_allocator::dealloc_frame(f);
-        println!("Deallocated frame 1");
+        debug!("Deallocated frame 1");
     }
 
     let frame3 = mm::frame_allocator::alloc_frame();
-    println!(
+    debug!(
         "Allocated frame 3: {:?} (Should be same as frame 1)",
         frame3
     );
     // ---
 
     let b = Box::new(42);
-    println!("Box value: {}", b);
+    debug!("Box value: {}", b);
 
     let mut v = Vec::new();
     for i in 0..5 {
@@ -81,9 +126,9 @@ pub extern "C" fn rust_main() -> ! {
     }
     debug!("Vec: {:?}", v);
 
-    println!("Initializing tasks...");
+    info!("Initializing tasks...");
     task::init();
-    println!("Starting first task...");
+    info!("Starting first task...");
     manager::run_first_task();
 
     panic!("Unreachable in rust_main!");
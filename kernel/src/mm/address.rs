@@ -1,4 +1,4 @@
-use crate::mm::page_table::PageTableEntry;
+use crate::mm::page_table::{levels, PageTableEntry};
 // 物理地址相关结构体
 // 物理页号
 #[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Debug)]
@@ -84,18 +84,26 @@ impl PhysPageNum {
         let pa: PhysAddr = (*self).into();
         unsafe { core::slice::from_raw_parts_mut(pa.0 as *mut PageTableEntry, 512) }
     }
+
+    /// 以字节数组的形式访问整个物理页 (内核是恒等映射的，可以直接用物理地址访问)
+    pub fn get_bytes_array(&self) -> &'static mut [u8] {
+        let pa: PhysAddr = (*self).into();
+        unsafe { core::slice::from_raw_parts_mut(pa.0 as *mut u8, PAGE_SIZE) }
+    }
+
+    /// 把物理页开头的内存当作一个 T 类型的对象访问
+    pub fn get_mut<T>(&self) -> &'static mut T {
+        let pa: PhysAddr = (*self).into();
+        unsafe { &mut *(pa.0 as *mut T) }
+    }
 }
 
 impl VirtPageNum {
-    pub fn indexes(&self) -> [usize; 3] {
-        let mut vpn = self.0;
-        let mut index = [0usize; 3];
-
-        for i in (0..3).rev() {
-            index[i] = vpn & 511; //取低9位
-            vpn >>= 9;
-        }
-        index
+    /// 各级页表中的下标 (每级取 9 位)，从根页表开始，个数是当前分页模式的级数
+    pub fn indexes(&self) -> impl Iterator<Item = usize> {
+        let vpn = self.0;
+        let levels = levels();
+        (0..levels).map(move |level| (vpn >> (9 * (levels - 1 - level))) & 511)
     }
 }
 
@@ -106,4 +114,8 @@ impl VirtAddr {
     pub fn ceil(&self) -> VirtPageNum {
         VirtPageNum((self.0 + PAGE_SIZE - 1) / PAGE_SIZE)
     }
+    /// 页内偏移
+    pub fn page_offset(&self) -> usize {
+        self.0 & (PAGE_SIZE - 1)
+    }
 }
@@ -22,7 +22,7 @@ impl StackFrameAllocator {
     fn init(&mut self, start: PhysPageNum, end: PhysPageNum) {
         self.current = start.0;
         self.end = end.0;
-        println!("Memory Area: [{:#x}, {:#x})", start.0, end.0);
+        info!("Memory Area: [{:#x}, {:#x})", start.0, end.0);
     }
 
     fn alloc(&mut self) -> Option<PhysPageNum> {
@@ -56,9 +56,9 @@ extern "C" {
 }
 
 pub fn init() {
-    println!("{}:{} start frame_allocatoe init!", file!(), line!());
+    debug!("start frame_allocator init");
     let ekernel_addr = unsafe { &ekernel as *const _ as usize };
-    println!("{} {}: ekernel_addr {}", file!(), line!(), ekernel_addr);
+    debug!("ekernel_addr {:#x}", ekernel_addr);
     // 向上取整到页边界
     let start_pa = (ekernel_addr + PAGE_SIZE - 1) / PAGE_SIZE;
 
@@ -69,8 +69,11 @@ pub fn init() {
     }
 }
 
+/// 分配一个物理页，页中的内容会被清零 (回收的页上可能残留着旧数据)
 pub fn alloc_frame() -> Option<PhysPageNum> {
-    unsafe { FRAME_ALLOCATOR.alloc() }
+    let ppn = unsafe { FRAME_ALLOCATOR.alloc() }?;
+    ppn.get_bytes_array().fill(0);
+    Some(ppn)
 }
 
 pub fn dealloc_frame(ppn: PhysPageNum) {
@@ -1,14 +1,18 @@
-use crate::mm::address::{PhysPageNum, VirtAddr, VirtPageNum};
+use crate::board;
+use crate::config::TRAMPOLINE;
+use crate::mm::address::{PhysAddr, PhysPageNum, VirtAddr, VirtPageNum, PAGE_SIZE};
 use crate::mm::frame_allocator::{alloc_frame, dealloc_frame};
-use crate::mm::page_table::{PTEFlags, PageTable};
+use crate::mm::page_table::{level_pages, levels, PTEFlags, PageTable, PageTableEntry};
 use alloc::collections::BTreeMap;
 use alloc::vec::Vec;
 use bitflags::bitflags;
+use lazy_static::lazy_static;
+use spin::Mutex;
 
 // 映射类型
 #[derive(Clone, Copy, PartialEq, Debug)]
 pub enum MapType {
-    Identical, // 恒等映射 (虚拟地址 = 物理地址)，用于内核
+    Identical, // 恒等映射 (虚拟地址 = 物理地址)，用于内核，对齐时自动使用大页
     Framed,    // 帧映射 (分配新物理页)，用于用户程序/栈/堆
 }
 
@@ -46,38 +50,113 @@ impl MapArea {
     }
 
     pub fn map(&mut self, page_table: &mut PageTable) {
+        let pte_flags = PTEFlags::from_bits(self.map_permission.bits()).unwrap();
+        if self.map_type == MapType::Identical {
+            for (vpn, level) in self.identical_chunks() {
+                page_table.map_huge(vpn, PhysPageNum(vpn.0), level, pte_flags);
+            }
+            return;
+        }
         for vpn_val in self.vpn_range.0 .0..self.vpn_range.1 .0 {
             let vpn = VirtPageNum(vpn_val);
-            let ppn = match self.map_type {
-                MapType::Identical => PhysPageNum(vpn_val),
-                MapType::Framed => {
-                    let frame = alloc_frame().expect("Out of merry!");
-                    self.data_frames.insert(vpn, frame);
-                    frame
-                }
-            };
-            let pte_flags = PTEFlags::from_bits(self.map_permission.bits()).unwrap();
-            page_table.map(vpn, ppn, pte_flags);
+            let frame = alloc_frame().expect("Out of merry!");
+            self.data_frames.insert(vpn, frame);
+            page_table.map(vpn, frame, pte_flags);
+        }
+    }
+
+    /// 恒等映射的区域尽量使用大页：从区域开头开始，每次取对齐且不超出区域的最大的页
+    /// 返回每一页的起始虚拟页号和它所在的页表级别
+    fn identical_chunks(&self) -> Vec<(VirtPageNum, usize)> {
+        let (start, end) = (self.vpn_range.0 .0, self.vpn_range.1 .0);
+        let mut chunks = Vec::new();
+        let mut vpn = start;
+        while vpn < end {
+            let level = (0..levels())
+                .find(|&level| {
+                    vpn.is_multiple_of(level_pages(level)) && vpn + level_pages(level) <= end
+                })
+                .unwrap();
+            chunks.push((VirtPageNum(vpn), level));
+            vpn += level_pages(level);
+        }
+        chunks
+    }
+
+    /// 把 data 复制到该区域开头的若干页中 (仅 Framed 模式，需要先 map)
+    pub fn copy_data(&mut self, data: &[u8]) {
+        assert_eq!(self.map_type, MapType::Framed);
+        for (i, chunk) in data.chunks(PAGE_SIZE).enumerate() {
+            let vpn = VirtPageNum(self.vpn_range.0 .0 + i);
+            let dst = &mut self.data_frames[&vpn].get_bytes_array()[..chunk.len()];
+            dst.copy_from_slice(chunk);
         }
     }
+
+    /// 打印这个区域：虚拟地址范围、映射类型、权限和已经分配了物理页的页数
+    fn dump(&self) {
+        let mut perm = *b"----";
+        for (i, (flag, c)) in [
+            (MapPermission::READ, b'R'),
+            (MapPermission::WRITE, b'W'),
+            (MapPermission::EXE, b'X'),
+            (MapPermission::U, b'U'),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            if self.map_permission.contains(flag) {
+                perm[i] = c;
+            }
+        }
+        let pages = self.vpn_range.1 .0 - self.vpn_range.0 .0;
+        // 恒等映射的区域不单独分配物理页，所有页都在内存中
+        let resident = match self.map_type {
+            MapType::Identical => pages,
+            MapType::Framed => self.data_frames.len(),
+        };
+        println!(
+            "  [{:#x}, {:#x}) {:?} {} {}/{} pages",
+            VirtAddr::from(self.vpn_range.0).0,
+            VirtAddr::from(self.vpn_range.1).0,
+            self.map_type,
+            core::str::from_utf8(&perm).unwrap(),
+            resident,
+            pages
+        );
+    }
+
     #[allow(unused)]
     pub fn unmap(&mut self, page_table: &mut PageTable) {
+        if self.map_type == MapType::Identical {
+            for (vpn, level) in self.identical_chunks() {
+                page_table.unmap_huge(vpn, level);
+            }
+            return;
+        }
         for vpn_val in self.vpn_range.0 .0..self.vpn_range.1 .0 {
             let vpn = VirtPageNum(vpn_val);
             page_table.unmap(vpn);
-            // 如果是 Framed，物理页会在 data_frames drop 时被释放（需要实现 Drop，或者手动释放）
-            // 这里我们暂时手动处理：
-            if self.map_type == MapType::Framed {
-                if let Some(ppn) = self.data_frames.remove(&vpn) {
-                    dealloc_frame(ppn);
-                }
+            // 取消映射后立即释放物理页，没有取消映射的页在区域被丢弃时释放
+            if let Some(ppn) = self.data_frames.remove(&vpn) {
+                dealloc_frame(ppn);
             }
         }
     }
 }
 
+// 区域被丢弃时释放还没有 unmap 的物理页 (只有 Framed 模式有)，页表项随页表一起释放
+impl Drop for MapArea {
+    fn drop(&mut self) {
+        for &frame in self.data_frames.values() {
+            dealloc_frame(frame);
+        }
+    }
+}
+
 extern "C" {
     fn stext();
+    fn strampoline();
     fn etext();
     fn srodata();
     fn erodata();
@@ -101,19 +180,69 @@ impl MemorySet {
         }
     }
 
-    pub fn push(&mut self, map_area: MapArea, _data: Option<&[u8]>) {
+    /// 映射一个区域，如果给出了 data，就把它复制到该区域中
+    pub fn push(&mut self, mut map_area: MapArea, data: Option<&[u8]>) {
+        map_area.map(&mut self.page_table);
+        if let Some(data) = data {
+            map_area.copy_data(data);
+        }
         self.areas.push(map_area);
     }
 
-    // 修改：统一映射所有区域
-    pub fn map_all_area(&mut self) {
-        for area in self.areas.iter_mut() {
-            area.map(&mut self.page_table);
+    /// 映射一个新分配物理页的区域 [start_va, end_va)
+    pub fn insert_framed_area(
+        &mut self,
+        start_va: VirtAddr,
+        end_va: VirtAddr,
+        permission: MapPermission,
+    ) {
+        self.push(
+            MapArea::new(start_va, end_va, MapType::Framed, permission),
+            None,
+        );
+    }
+
+    /// 取消从 start_vpn 开始的区域的映射，并释放它的物理页
+    pub fn remove_area_with_start_vpn(&mut self, start_vpn: VirtPageNum) {
+        if let Some(index) = self
+            .areas
+            .iter()
+            .position(|area| area.vpn_range.0 == start_vpn)
+        {
+            let mut area = self.areas.remove(index);
+            area.unmap(&mut self.page_table);
+        }
+    }
+
+    /// 映射跳板页：它位于每个地址空间的最高一页，内核和用户地址空间中的映射完全相同，
+    /// 因此在陷入和返回时切换 satp 的前后，跳板页上的代码都能继续执行
+    fn map_trampoline(&mut self) {
+        self.page_table.map(
+            VirtAddr(TRAMPOLINE).floor(),
+            PhysAddr(strampoline as *const () as usize).floor(),
+            PTEFlags::R | PTEFlags::X,
+        );
+    }
+
+    pub fn token(&self) -> usize {
+        self.page_table.token()
+    }
+
+    /// 打印地址空间中的所有区域 (不包括跳板页，它直接映射在页表中)
+    pub fn dump(&self) {
+        println!("memory set (satp {:#x}):", self.token());
+        for area in &self.areas {
+            area.dump();
         }
     }
 
+    pub fn translate(&self, vpn: VirtPageNum) -> Option<PageTableEntry> {
+        self.page_table.translate(vpn)
+    }
+
     pub fn new_kernel() -> Self {
         let mut memory_set = Self::new_bare();
+        memory_set.map_trampoline();
         // 这里我们需要获取链接脚本中定义的各个段的地址
         // 使用 usize 获取地址
         let stext_addr = stext as *const () as usize;
@@ -126,7 +255,7 @@ impl MemorySet {
         let ebss_addr = ebss as *const () as usize;
         let ekernel_addr = ekernel as *const () as usize;
 
-        println!("mapping .text section");
+        debug!("mapping .text section");
         memory_set.push(
             MapArea::new(
                 VirtAddr(stext_addr),
@@ -137,7 +266,7 @@ impl MemorySet {
             None,
         );
 
-        println!("mapping .rodata section");
+        debug!("mapping .rodata section");
         memory_set.push(
             MapArea::new(
                 VirtAddr(srodata_addr),
@@ -148,7 +277,7 @@ impl MemorySet {
             None,
         );
 
-        println!("mapping .data section");
+        debug!("mapping .data section");
         memory_set.push(
             MapArea::new(
                 VirtAddr(sdata_addr),
@@ -159,7 +288,7 @@ impl MemorySet {
             None,
         );
 
-        println!("mapping .bss section");
+        debug!("mapping .bss section");
         memory_set.push(
             MapArea::new(
                 VirtAddr(sbss_addr),
@@ -170,7 +299,7 @@ impl MemorySet {
             None,
         );
 
-        println!("mapping physical memory");
+        debug!("mapping physical memory");
         // 映射剩余的物理内存（包括堆、分配器管理的空闲页）
         // 简单起见，我们映射到内存的高地址，或者直接映射整个可用物理内存
         // 这里我们映射 ekernel 到 物理内存结束（假设 128MB 内存）
@@ -186,15 +315,73 @@ impl MemorySet {
             None,
         );
 
-        memory_set.map_all_area();
+        debug!("mapping MMIO regions");
+        // 设备寄存器也使用恒等映射，驱动直接用设备树中的物理地址访问
+        for region in board::mmio_regions() {
+            memory_set.push(
+                MapArea::new(
+                    VirtAddr(region.base),
+                    VirtAddr(region.base + region.size),
+                    MapType::Identical,
+                    MapPermission::READ | MapPermission::WRITE,
+                ),
+                None,
+            );
+        }
+
         memory_set
     }
 
+    /// 根据用户程序的 ELF 创建它的地址空间
+    /// 返回 (地址空间, 用户栈的起始地址, 入口地址)
+    pub fn from_elf(elf_data: &[u8]) -> (Self, usize, usize) {
+        let mut memory_set = Self::new_bare();
+        memory_set.map_trampoline();
+
+        let elf = xmas_elf::ElfFile::new(elf_data).expect("invalid elf");
+        assert_eq!(
+            elf.header.pt1.magic,
+            [0x7f, b'E', b'L', b'F'],
+            "invalid elf magic"
+        );
+
+        // 映射每个 LOAD 段，权限由段的标志决定
+        let mut max_end_vpn = VirtPageNum(0);
+        for ph in elf.program_iter() {
+            if ph.get_type() != Ok(xmas_elf::program::Type::Load) {
+                continue;
+            }
+            let start_va = VirtAddr(ph.virtual_addr() as usize);
+            let end_va = VirtAddr((ph.virtual_addr() + ph.mem_size()) as usize);
+            let mut map_permission = MapPermission::U;
+            let flags = ph.flags();
+            if flags.is_read() {
+                map_permission |= MapPermission::READ;
+            }
+            if flags.is_write() {
+                map_permission |= MapPermission::WRITE;
+            }
+            if flags.is_execute() {
+                map_permission |= MapPermission::EXE;
+            }
+            let map_area = MapArea::new(start_va, end_va, MapType::Framed, map_permission);
+            max_end_vpn = max_end_vpn.max(map_area.vpn_range.1);
+            let offset = ph.offset() as usize;
+            let file_size = ph.file_size() as usize;
+            memory_set.push(map_area, Some(&elf_data[offset..offset + file_size]));
+        }
+
+        // 线程的用户栈从程序最后一个段之后开始排列，中间空出一页作为保护页
+        // 用户栈和 TrapContext 在创建线程时才映射
+        let user_stack_base = VirtAddr::from(max_end_vpn).0 + PAGE_SIZE;
+
+        let entry_point = elf.header.pt2.entry_point() as usize;
+        (memory_set, user_stack_base, entry_point)
+    }
+
     // 激活页表
     pub fn activate(&self) {
-        let satp = self.page_table.root_ppn().0;
-        // 模式 8 代表 SV39
-        let satp_val = 8usize << 60 | satp;
+        let satp_val = self.page_table.token();
         unsafe {
             // 写入 satp 寄存器
             core::arch::asm!("csrw satp, {}", in(reg) satp_val);
@@ -203,3 +390,13 @@ impl MemorySet {
         }
     }
 }
+
+lazy_static! {
+    /// 内核地址空间，每个用户地址空间之外还需要一直保留着它
+    pub static ref KERNEL_SPACE: Mutex<MemorySet> = Mutex::new(MemorySet::new_kernel());
+}
+
+/// 内核地址空间的 token，陷入内核时写入 satp
+pub fn kernel_token() -> usize {
+    KERNEL_SPACE.lock().token()
+}
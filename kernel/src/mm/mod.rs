@@ -5,13 +5,14 @@ pub mod memory_set;
 pub mod page_table;
 
 pub fn init() {
-    println!("mm init");
+    info!("mm init");
     frame_allocator::init();
     heap_allocator::init_heap();
+    page_table::probe_paging_mode();
+    info!("Paging mode: {}", page_table::paging_mode_name());
 
     // 初始化内核地址空间并激活分页！
-    println!("Initializing kernel address space...");
-    let kernel_memory_set = memory_set::MemorySet::new_kernel();
-    kernel_memory_set.activate();
-    println!("Paging enabled!");
+    info!("Initializing kernel address space...");
+    memory_set::KERNEL_SPACE.lock().activate();
+    info!("Paging enabled!");
 }
@@ -1,12 +1,14 @@
 use crate::mm::{
-    address::{PhysPageNum, VirtPageNum},
-    frame_allocator::alloc_frame,
+    address::{PhysAddr, PhysPageNum, VirtAddr, VirtPageNum, PAGE_SIZE, PAGE_SIZE_BITS},
+    frame_allocator::{alloc_frame, dealloc_frame},
 };
 use alloc::vec;
 use alloc::vec::Vec;
 use bitflags::bitflags;
+use core::arch::asm;
+use core::sync::atomic::{AtomicUsize, Ordering};
 
-// 在 RISC-V 的 SV39 分页模式下，一个页表项（PTE）是一个 64位 的整数，
+// 在 RISC-V 的 SV39 / SV48 / SV57 分页模式下，页表项的格式相同：一个页表项（PTE）是一个 64位 的整数，
 // 它的每一位布局如下。
 // [63:54]: 保留位 (Reserved)，必须为 0。
 // [53:10]: 物理页号 (PPN)。共 44 位。这是映射到的物理地址的高位部分。
@@ -20,6 +22,7 @@ use bitflags::bitflags;
 // D (Dirty): CPU 写入过该页时会自动置 1。
 
 bitflags! {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
     pub struct PTEFlags:u8{
         const V = 1 << 0; // Valid
         const R = 1 << 1; // Read
@@ -66,14 +69,147 @@ impl PageTableEntry {
     pub fn is_valid(&self) -> bool {
         !(self.flags() & PTEFlags::V).is_empty()
     }
+    /// R/W/X 都为 0 的有效页表项指向下一级页表，否则是叶子，直接映射一个 (大) 页
+    pub fn is_leaf(&self) -> bool {
+        self.flags()
+            .intersects(PTEFlags::R | PTEFlags::W | PTEFlags::X)
+    }
+}
+
+/// 把标志位打印成 "DAGUXWRV" 的形式，没有设置的位显示为 '-'
+pub fn flags_str(flags: PTEFlags) -> [u8; 8] {
+    let mut s = *b"--------";
+    for (i, c) in b"DAGUXWRV".iter().enumerate() {
+        if flags.bits() & (1 << (7 - i)) != 0 {
+            s[i] = *c;
+        }
+    }
+    s
+}
+
+// 分页模式：SV39 / SV48 / SV57 分别有 3 / 4 / 5 级页表，每级用虚拟页号中的 9 位作为下标
+// 启动时由 probe_paging_mode 选出硬件支持的最深的模式，之后不再改变
+pub const MAX_LEVELS: usize = 5;
+static PAGING_LEVELS: AtomicUsize = AtomicUsize::new(3);
+
+/// 当前分页模式的页表级数
+pub fn levels() -> usize {
+    PAGING_LEVELS.load(Ordering::Relaxed)
+}
+
+/// 分页模式的名字，例如 "Sv48"
+pub fn paging_mode_name() -> &'static str {
+    ["Sv39", "Sv48", "Sv57"][levels() - 3]
+}
+
+/// 第 level 级 (0 为根页表) 的一个叶子页表项映射的页数：最后一级 4 KiB，往上每级乘 512 (2 MiB、1 GiB ...)
+pub fn level_pages(level: usize) -> usize {
+    1 << (9 * (levels() - 1 - level))
+}
+
+/// 页表中的虚拟地址只有低 12 + 9 * 级数 位，最高位为 1 时高位要符号扩展 (例如跳板页)
+fn sign_extend(va: usize) -> usize {
+    let va_bits = PAGE_SIZE_BITS + 9 * levels();
+    if va & (1 << (va_bits - 1)) != 0 {
+        va | !((1 << va_bits) - 1)
+    } else {
+        va
+    }
+}
+
+/// 用户地址空间的上界：低半部分的规范地址 [0, 2^(va_bits - 1))，更高的地址不属于用户程序
+pub fn user_va_end() -> usize {
+    1 << (PAGE_SIZE_BITS + 9 * levels() - 1)
+}
+
+/// dump 时合并的一段叶子映射：虚拟地址和物理地址都连续，标志位相同
+struct LeafRun {
+    va: usize,
+    pa: usize,
+    size: usize,
+    flags: PTEFlags,
+}
+
+impl LeafRun {
+    fn print(&self, level: usize) {
+        let flags = flags_str(self.flags);
+        println!(
+            "{:indent$}[{:#x}, {:#x}] -> {:#x} {} ({} KiB)",
+            "",
+            sign_extend(self.va),
+            // 打印包含在内的最后一个地址，地址空间最高处的跳板页加上大小会溢出
+            sign_extend(self.va) + (self.size - 1),
+            self.pa,
+            core::str::from_utf8(&flags).unwrap(),
+            self.size / 1024,
+            indent = level * 2 + 2
+        );
+    }
+}
+
+// satp 寄存器：[63:60] 是分页模式 (8 为 SV39，9 为 SV48，10 为 SV57)，[43:0] 是根页表的物理页号
+const SATP_PPN_MASK: usize = (1 << 44) - 1;
+
+/// levels 级页表对应的 satp 模式
+fn satp_mode(levels: usize) -> usize {
+    levels + 5
+}
+
+/// 启动时探测硬件支持的分页模式：satp 写入不支持的模式时整个写操作无效，读回来就能知道是否支持
+/// 探测用的根页表只有一个大页叶子，恒等映射内核所在的区域，切换过去之后代码和栈仍然可以访问
+/// 必须在建立内核地址空间之前调用
+pub fn probe_paging_mode() {
+    let root = alloc_frame().expect("No frames for page table");
+    let kernel = probe_paging_mode as *const () as usize;
+    let levels = (3..=MAX_LEVELS)
+        .rev()
+        .find(|&levels| {
+            // 根页表中一项覆盖的地址位数
+            let shift = PAGE_SIZE_BITS + 9 * (levels - 1);
+            let ptes = root.get_pte_array();
+            ptes.fill(PageTableEntry::empty());
+            ptes[(kernel >> shift) & 511] = PageTableEntry::new(
+                PhysPageNum(kernel >> shift << (shift - PAGE_SIZE_BITS)),
+                PTEFlags::V | PTEFlags::R | PTEFlags::W | PTEFlags::X | PTEFlags::A | PTEFlags::D,
+            );
+            let satp = satp_mode(levels) << 60 | root.0;
+            let read_back: usize;
+            // 切换、读回、切回 bare 模式在一段汇编中完成
+            unsafe {
+                asm!(
+                    "csrw satp, {satp}",
+                    "sfence.vma",
+                    "csrr {read_back}, satp",
+                    "csrw satp, zero",
+                    "sfence.vma",
+                    satp = in(reg) satp,
+                    read_back = out(reg) read_back,
+                );
+            }
+            read_back == satp
+        })
+        .expect("SV39 is not supported");
+    dealloc_frame(root);
+    PAGING_LEVELS.store(levels, Ordering::Relaxed);
 }
 
 // 页表结构体
+// frames 保存页表自己占用的物理页；from_token 得到的只是别人页表的视图，frames 为空
 
 pub struct PageTable {
     root_ppn: PhysPageNum,
     frames: Vec<PhysPageNum>,
 }
+
+// 页表被丢弃时 (例如进程被回收) 释放它占用的物理页；视图的 frames 为空，不会释放别人的页表
+impl Drop for PageTable {
+    fn drop(&mut self) {
+        for &frame in &self.frames {
+            dealloc_frame(frame);
+        }
+    }
+}
+
 impl PageTable {
     pub fn new() -> Self {
         let frame = alloc_frame().expect("No frames for page table");
@@ -83,21 +219,36 @@ impl PageTable {
         }
     }
 
-    /// 获取根页表的物理页号（用于写入 satp 寄存器）
-    pub fn root_ppn(&self) -> PhysPageNum {
-        self.root_ppn
+    /// 根据 satp 的值得到一个地址空间页表的视图，用来查询其他地址空间 (例如其他进程) 的映射
+    /// 视图不拥有页表占用的物理页，只能查询，不能用来修改映射
+    /// satp 没有开启分页 (Bare 模式) 或者分页模式与当前使用的不同时返回 None
+    pub fn from_token(satp: usize) -> Option<Self> {
+        if satp >> 60 != satp_mode(levels()) {
+            return None;
+        }
+        Some(PageTable {
+            root_ppn: PhysPageNum(satp & SATP_PPN_MASK),
+            frames: Vec::new(),
+        })
     }
 
-    fn find_pte(&mut self, vpn: VirtPageNum, create: bool) -> Option<&mut PageTableEntry> {
-        let idxs = vpn.indexes();
-        let mut ppn = self.root_ppn;
-        let result: Option<&mut PageTableEntry> = None;
-
-        for (i, &idx) in idxs.iter().enumerate() {
-            let pte_array = ppn.get_pte_array();
-            let pte = &mut pte_array[idx];
+    /// 写入 satp 寄存器的值：高 4 位是当前的分页模式，低 44 位是根页表的物理页号
+    pub fn token(&self) -> usize {
+        satp_mode(levels()) << 60 | self.root_ppn.0
+    }
 
-            if i == 2 {
+    /// 找到 vpn 在第 level 级 (0 为根页表) 的页表项，create 为 true 时创建途中缺少的页表
+    /// 途中遇到大页的叶子说明 vpn 已经被映射了，直接 panic
+    fn find_pte(
+        &mut self,
+        vpn: VirtPageNum,
+        level: usize,
+        create: bool,
+    ) -> Option<&mut PageTableEntry> {
+        let mut ppn = self.root_ppn;
+        for (i, idx) in vpn.indexes().enumerate() {
+            let pte = &mut ppn.get_pte_array()[idx];
+            if i == level {
                 return Some(pte);
             }
 
@@ -108,15 +259,32 @@ impl PageTable {
                 let frame = alloc_frame()?;
                 *pte = PageTableEntry::new(frame, PTEFlags::V);
                 self.frames.push(frame);
+            } else if pte.is_leaf() {
+                panic!("VPN {:?} is inside a huge page", vpn);
             }
             ppn = pte.ppn();
         }
-        result
+        unreachable!()
     }
 
     // 建立映射
     pub fn map(&mut self, vpn: VirtPageNum, ppn: PhysPageNum, flags: PTEFlags) {
-        let pte = self.find_pte(vpn, true).expect("Map failed: no frames");
+        self.map_huge(vpn, ppn, levels() - 1, flags);
+    }
+
+    /// 在第 level 级建立叶子页表项，映射 level_pages(level) 个页 (倒数第二级是 2 MiB，倒数第三级是 1 GiB)
+    /// vpn 和 ppn 都必须按大页的大小对齐
+    pub fn map_huge(&mut self, vpn: VirtPageNum, ppn: PhysPageNum, level: usize, flags: PTEFlags) {
+        let pages = level_pages(level);
+        assert!(
+            vpn.0.is_multiple_of(pages) && ppn.0.is_multiple_of(pages),
+            "huge page {:?} -> {:?} is not aligned",
+            vpn,
+            ppn
+        );
+        let pte = self
+            .find_pte(vpn, level, true)
+            .expect("Map failed: no frames");
         if pte.is_valid() {
             panic!("VPN {:?} is already mapped", vpn);
         }
@@ -125,7 +293,14 @@ impl PageTable {
 
     // 解除映射
     pub fn unmap(&mut self, vpn: VirtPageNum) {
-        let pte = self.find_pte(vpn, false).expect("Unmap failed: not mapped");
+        self.unmap_huge(vpn, levels() - 1);
+    }
+
+    /// 解除 map_huge 在第 level 级建立的映射
+    pub fn unmap_huge(&mut self, vpn: VirtPageNum, level: usize) {
+        let pte = self
+            .find_pte(vpn, level, false)
+            .expect("Unmap failed: not mapped");
         if !pte.is_valid() {
             panic!("VPN {:?} is invalid", vpn);
         }
@@ -133,27 +308,134 @@ impl PageTable {
     }
 
     /// 查找虚拟页号对应的物理页表项
+    /// vpn 在大页中时，返回的页表项的 ppn 是 vpn 对应的那个 4 KiB 物理页，标志位与大页相同
     pub fn translate(&self, vpn: VirtPageNum) -> Option<PageTableEntry> {
-        let idxs = vpn.indexes();
-        let mut ppn = self.root_ppn;
+        // walk 在无效的页表项或者叶子处停止，最后一个页表项就是查找的结果
+        let (level, pte) = self
+            .walk(vpn)
+            .into_iter()
+            .enumerate()
+            .filter_map(|(level, pte)| Some((level, pte?)))
+            .next_back()?;
+        if !pte.is_valid() || !pte.is_leaf() {
+            return None;
+        }
+        let offset = vpn.0 % level_pages(level);
+        Some(PageTableEntry::new(
+            PhysPageNum(pte.ppn().0 + offset),
+            pte.flags(),
+        ))
+    }
 
-        for (i, &idx) in idxs.iter().enumerate() {
-            let pte_array = ppn.get_pte_array();
-            let pte = &pte_array[idx];
+    /// 用户态可以按 access (R / W) 访问的虚拟页对应的页表项
+    pub fn translate_user(&self, vpn: VirtPageNum, access: PTEFlags) -> Option<PageTableEntry> {
+        // 查页表时每级只取 9 位下标，超出用户地址空间的页号会被截断成别的页，要先排除
+        if vpn.0 >= user_va_end() >> PAGE_SIZE_BITS {
+            return None;
+        }
+        self.translate(vpn)
+            .filter(|pte| pte.flags().contains(PTEFlags::U | access))
+    }
 
-            if i == 2 {
-                if pte.is_valid() {
-                    return Some(*pte);
-                } else {
-                    return None;
+    /// 把用户地址空间中 [ptr, ptr + len) 的缓冲区转换成若干段内核可以直接访问的切片
+    /// (用户缓冲区在物理上不一定连续，每一页对应一段)
+    /// 缓冲区超出用户地址空间，或者有未映射的页、用户态不能访问或者没有 access 权限的页时返回 None
+    pub fn translated_byte_buffer(
+        &self,
+        ptr: usize,
+        len: usize,
+        access: PTEFlags,
+    ) -> Option<Vec<&'static mut [u8]>> {
+        let mut start = ptr;
+        let end = ptr.checked_add(len)?;
+        if end > user_va_end() {
+            return None;
+        }
+        let mut buffers = Vec::new();
+        while start < end {
+            let start_va = VirtAddr(start);
+            let vpn = start_va.floor();
+            let pte = self.translate_user(vpn, access)?;
+            let page_end = (VirtAddr::from(vpn).0 + PAGE_SIZE).min(end);
+            let page = pte.ppn().get_bytes_array();
+            buffers
+                .push(&mut page[start_va.page_offset()..start_va.page_offset() + page_end - start]);
+            start = page_end;
+        }
+        Some(buffers)
+    }
+
+    /// 打印页表中所有有效的页表项：指向下一级页表的项每项一行，然后缩进打印下一级；
+    /// 同一张页表中虚拟地址和物理地址都连续、标志位相同的叶子合并成一行
+    pub fn dump(&self) {
+        println!(
+            "page table {:#x} (satp {:#x}):",
+            PhysAddr::from(self.root_ppn).0,
+            self.token()
+        );
+        Self::dump_table(self.root_ppn, 0, 0);
+    }
+
+    /// 打印第 level 级 (0 为根页表) 的页表 ppn，它覆盖的虚拟地址从 va_base 开始
+    fn dump_table(ppn: PhysPageNum, level: usize, va_base: usize) {
+        // 这一级每个页表项覆盖的大小
+        let entry_size = level_pages(level) * PAGE_SIZE;
+        let mut run: Option<LeafRun> = None;
+        for (idx, pte) in ppn.get_pte_array().iter().enumerate() {
+            let va = va_base + idx * entry_size;
+            if pte.is_valid() && pte.is_leaf() {
+                let pa = PhysAddr::from(pte.ppn()).0;
+                let flags = pte.flags();
+                if let Some(run) = run.as_mut().filter(|run| {
+                    run.va + run.size == va && run.pa + run.size == pa && run.flags == flags
+                }) {
+                    run.size += entry_size;
+                    continue;
+                }
+                if let Some(run) = run.replace(LeafRun {
+                    va,
+                    pa,
+                    size: entry_size,
+                    flags,
+                }) {
+                    run.print(level);
                 }
+                continue;
             }
+            if let Some(run) = run.take() {
+                run.print(level);
+            }
+            if pte.is_valid() {
+                println!(
+                    "{:indent$}L{} [{}] {:#x}: table {:#x}",
+                    "",
+                    levels() - 1 - level,
+                    idx,
+                    sign_extend(va),
+                    PhysAddr::from(pte.ppn()).0,
+                    indent = level * 2 + 2
+                );
+                Self::dump_table(pte.ppn(), level + 1, va);
+            }
+        }
+        if let Some(run) = run {
+            run.print(level);
+        }
+    }
 
-            if !pte.is_valid() {
-                return None;
+    /// 逐级查找 vpn，返回每一级 (从根页表开始) 经过的页表项
+    /// 遇到无效的页表项或者大页的叶子时停止，之后的级别为 None
+    pub fn walk(&self, vpn: VirtPageNum) -> [Option<PageTableEntry>; MAX_LEVELS] {
+        let mut ptes = [None; MAX_LEVELS];
+        let mut ppn = self.root_ppn;
+        for (i, idx) in vpn.indexes().enumerate() {
+            let pte = ppn.get_pte_array()[idx];
+            ptes[i] = Some(pte);
+            if !pte.is_valid() || pte.is_leaf() {
+                break;
             }
             ppn = pte.ppn();
         }
-        None
+        ptes
     }
 }
@@ -1,12 +1,27 @@
 #![allow(unused)]
 
 use core::arch::asm;
+use spin::Once;
 
 // Legacy SBI Extension IDs
+const SBI_SET_TIMER: usize = 0;
 const SBI_CONSOLE_PUTCHAR: usize = 1;
 const SBI_CONSOLE_GETCHAR: usize = 2;
 const SBI_SHUTDOWN: usize = 8;
 
+// SBI v0.2+ Extension IDs
+const SBI_EXT_BASE: usize = 0x10;
+const SBI_EXT_DBCN: usize = 0x4442_434E; // "DBCN"
+const SBI_EXT_TIME: usize = 0x5449_4D45; // "TIME"
+
+// Base 扩展的 Function ID
+const SBI_BASE_PROBE_EXTENSION: usize = 3;
+
+// DBCN (Debug Console) 扩展的 Function ID
+const SBI_DBCN_CONSOLE_WRITE: usize = 0;
+const SBI_DBCN_CONSOLE_READ: usize = 1;
+const SBI_DBCN_CONSOLE_WRITE_BYTE: usize = 2;
+
 /// 调用 SBI 服务
 /// which: 服务 ID (Extension ID)
 /// arg0, arg1, arg2: 传递给 SBI 的参数
@@ -26,11 +41,96 @@ fn sbi_call(which: usize, arg0: usize, arg1: usize, arg2: usize) -> usize {
     ret
 }
 
+/// SBI v0.2+ 调用的返回值
+/// error: 错误码 (0 表示成功，负数表示失败)
+/// value: 返回的数据
+#[derive(Clone, Copy, Debug)]
+struct SbiRet {
+    error: isize,
+    value: usize,
+}
+
+/// 按照 SBI v0.2+ 的调用约定调用 SBI 服务
+/// eid 放入 a7，fid 放入 a6，返回值为 (a0, a1) = (error, value)
+#[inline(always)]
+fn sbi_call_ext(eid: usize, fid: usize, arg0: usize, arg1: usize, arg2: usize) -> SbiRet {
+    let (error, value);
+    unsafe {
+        asm!(
+            "ecall",
+            inlateout("a0") arg0 => error,
+            inlateout("a1") arg1 => value,
+            in("a2") arg2,
+            in("a6") fid,
+            in("a7") eid,
+        )
+    }
+    SbiRet { error, value }
+}
+
+/// 探测 SBI 实现是否支持某个扩展
+fn probe_extension(eid: usize) -> bool {
+    let ret = sbi_call_ext(SBI_EXT_BASE, SBI_BASE_PROBE_EXTENSION, eid, 0, 0);
+    // 旧版 SBI (v0.1) 不认识 Base 扩展，会返回错误
+    ret.error == 0 && ret.value != 0
+}
+
+// TIME 扩展的 Function ID
+const SBI_TIME_SET_TIMER: usize = 0;
+
+// DBCN 扩展是否可用，只在第一次输出时探测一次
+static DBCN_AVAILABLE: Once<bool> = Once::new();
+
+fn dbcn_available() -> bool {
+    *DBCN_AVAILABLE.call_once(|| probe_extension(SBI_EXT_DBCN))
+}
+
 /// 向控制台输出一个字符
 pub fn console_putchar(c: usize) {
     sbi_call(SBI_CONSOLE_PUTCHAR, c, 0, 0);
 }
 
+/// 向控制台输出一段字节
+/// 优先使用 DBCN 扩展一次性写出整个缓冲区，不支持时退回到逐字节的 legacy putchar
+pub fn console_write(bytes: &[u8]) {
+    let mut rest = bytes;
+    if dbcn_available() {
+        while !rest.is_empty() {
+            // DBCN 需要缓冲区的物理地址。内核地址空间是恒等映射的，
+            // 所以内核中的虚拟地址就是物理地址，且连续的虚拟内存在物理上也连续
+            let base = rest.as_ptr() as usize;
+            let ret = sbi_call_ext(
+                SBI_EXT_DBCN,
+                SBI_DBCN_CONSOLE_WRITE,
+                rest.len(),
+                base,
+                0, // 物理地址高 64 位 (RV64 下总是 0)
+            );
+            // 出错 (或没有任何进展) 时把剩下的字节交给 legacy 路径
+            if ret.error != 0 || ret.value == 0 {
+                break;
+            }
+            // SBI 可能只写出了一部分，继续写剩下的
+            rest = &rest[ret.value.min(rest.len())..];
+        }
+    }
+    for &b in rest {
+        console_putchar(b as usize);
+    }
+}
+
+// TIME 扩展是否可用，只在第一次设置时钟时探测一次
+static TIME_AVAILABLE: Once<bool> = Once::new();
+
+/// 设置下一次时钟中断的时间 (time CSR 的值)，同时清除当前挂起的时钟中断
+pub fn set_timer(stime_value: usize) {
+    if *TIME_AVAILABLE.call_once(|| probe_extension(SBI_EXT_TIME)) {
+        sbi_call_ext(SBI_EXT_TIME, SBI_TIME_SET_TIMER, stime_value, 0, 0);
+    } else {
+        sbi_call(SBI_SET_TIMER, stime_value, 0, 0);
+    }
+}
+
 /// 从控制台读取一个字符
 pub fn console_getchar() -> usize {
     sbi_call(SBI_CONSOLE_GETCHAR, 0, 0, 0)
@@ -0,0 +1,23 @@
+use super::{Mutex, WaitQueue};
+
+/// 条件变量，和 Mutex 配合使用
+/// 和 POSIX 一样，wait 返回时条件不一定成立，调用者要在循环中重新检查
+#[derive(Default)]
+pub struct Condvar {
+    wait_queue: WaitQueue,
+}
+
+impl Condvar {
+    /// 唤醒一个等待的任务
+    pub fn signal(&self) {
+        self.wait_queue.notify_one();
+    }
+
+    /// 释放 mutex 并阻塞，被唤醒后重新获得 mutex 再返回
+    /// 调用者必须持有 mutex；unlock 只唤醒任务不切换，进入等待队列之前不会错过 signal
+    pub fn wait(&self, mutex: &Mutex) {
+        mutex.unlock();
+        self.wait_queue.wait();
+        mutex.lock();
+    }
+}
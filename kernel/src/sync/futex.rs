@@ -0,0 +1,141 @@
+use super::IrqSafeSpinLock;
+use crate::task::manager::{block_current_and_run_next, current_task_id, wakeup_task};
+use crate::timer;
+use alloc::collections::{BTreeMap, VecDeque};
+use core::sync::atomic::{AtomicU32, Ordering};
+
+// futex 以用户变量的物理地址为键，映射到同一物理页的不同地址空间 (共享内存) 使用同一个等待队列
+// 一个任务同一时间只会等待一个 futex，requeue 会把它移到另一个键下，所以另外记录每个任务所在的键
+struct FutexTable {
+    queues: BTreeMap<usize, VecDeque<usize>>, // 物理地址 -> 等待的任务
+    waiting: BTreeMap<usize, usize>,          // 任务 -> 它所在队列的物理地址
+}
+
+impl FutexTable {
+    fn push(&mut self, key: usize, task_id: usize) {
+        self.queues.entry(key).or_default().push_back(task_id);
+        self.waiting.insert(task_id, key);
+    }
+
+    /// 从 key 的队列中取出最早等待的任务
+    fn pop(&mut self, key: usize) -> Option<usize> {
+        let queue = self.queues.get_mut(&key)?;
+        let task_id = queue.pop_front();
+        if queue.is_empty() {
+            self.queues.remove(&key);
+        }
+        if let Some(task_id) = task_id {
+            self.waiting.remove(&task_id);
+        }
+        task_id
+    }
+
+    /// 唤醒最多 count 个等待 key 的任务，返回唤醒的任务数
+    fn wake(&mut self, key: usize, count: usize) -> usize {
+        let mut woken = 0;
+        while woken < count {
+            let Some(task_id) = self.pop(key) else {
+                break;
+            };
+            wakeup_task(task_id);
+            woken += 1;
+        }
+        woken
+    }
+
+    /// 把任务从它所在的队列中移除，返回它是否还在队列中
+    fn remove(&mut self, task_id: usize) -> bool {
+        let Some(key) = self.waiting.remove(&task_id) else {
+            return false;
+        };
+        let queue = self.queues.get_mut(&key).unwrap();
+        queue.retain(|&id| id != task_id);
+        if queue.is_empty() {
+            self.queues.remove(&key);
+        }
+        true
+    }
+}
+
+static FUTEXES: IrqSafeSpinLock<FutexTable> = IrqSafeSpinLock::new(FutexTable {
+    queues: BTreeMap::new(),
+    waiting: BTreeMap::new(),
+});
+
+/// 读取物理地址 key 处的 u32 (内核是恒等映射的)，key 必须 4 字节对齐
+fn load(key: usize) -> u32 {
+    unsafe { (*(key as *const AtomicU32)).load(Ordering::SeqCst) }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FutexWait {
+    Woken,
+    Mismatch, // 变量的值不等于 expected，没有睡眠
+    TimedOut,
+}
+
+/// 如果物理地址 key 处的值等于 expected，阻塞当前任务直到被 wake 唤醒
+/// 或者单调时钟到达 deadline (纳秒)；检查和进入队列在同一次加锁中完成，不会丢失唤醒
+pub fn wait(key: usize, expected: u32, deadline: Option<u64>) -> FutexWait {
+    let current = current_task_id();
+    let mut futexes = FUTEXES.lock();
+    if load(key) != expected {
+        return FutexWait::Mismatch;
+    }
+    if deadline.is_some_and(|deadline| timer::monotonic_ns() >= deadline) {
+        return FutexWait::TimedOut;
+    }
+    futexes.push(key, current);
+    drop(futexes);
+
+    let timer = deadline.map(|deadline| timer::wakeup_at(deadline, current));
+    block_current_and_run_next();
+    if let Some(timer) = timer {
+        timer::cancel_timer(timer);
+    }
+    // 还在队列中说明不是被 wake 唤醒的
+    if FUTEXES.lock().remove(current) {
+        FutexWait::TimedOut
+    } else {
+        FutexWait::Woken
+    }
+}
+
+/// 唤醒最多 count 个等待物理地址 key 的任务，返回唤醒的任务数
+pub fn wake(key: usize, count: usize) -> usize {
+    FUTEXES.lock().wake(key, count)
+}
+
+/// 把退出的任务从它们所在的 futex 队列中移除，之后的 wake 和 requeue 不会再把它们算进去
+pub fn remove_waiters(task_ids: &[usize]) {
+    let mut futexes = FUTEXES.lock();
+    for &task_id in task_ids {
+        futexes.remove(task_id);
+    }
+}
+
+/// 唤醒最多 wake_count 个等待 key 的任务，再把最多 requeue_count 个剩下的任务移到 target 的队列中
+/// expected 不为 None 时先检查 key 处的值，不相等时什么也不做，返回 None
+/// 返回唤醒和移动的任务总数
+pub fn requeue(
+    key: usize,
+    target: usize,
+    wake_count: usize,
+    requeue_count: usize,
+    expected: Option<u32>,
+) -> Option<usize> {
+    let mut futexes = FUTEXES.lock();
+    if expected.is_some_and(|expected| load(key) != expected) {
+        return None;
+    }
+    let woken = futexes.wake(key, wake_count);
+    let mut moved = 0;
+    while moved < requeue_count {
+        let Some(task_id) = futexes.pop(key) else {
+            break;
+        };
+        futexes.push(target, task_id);
+        moved += 1;
+    }
+    Some(woken + moved)
+}
@@ -0,0 +1,104 @@
+use crate::cpu;
+use core::cell::UnsafeCell;
+use core::hint::spin_loop;
+use core::ops::{Deref, DerefMut};
+#[cfg(feature = "lockdep")]
+use core::panic::Location;
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+// 没有 hart 持有锁
+const NO_OWNER: usize = usize::MAX;
+
+/// 持有期间关闭当前 hart 中断的自旋锁
+/// 中断处理函数也会获取的锁 (如 TASK_MANAGER) 必须用它，否则中断打断持有者后会在同一个 hart 上死锁
+/// 记录持有锁的 hart，同一个 hart 重复加锁时直接 panic，而不是无声地死锁
+pub struct IrqSafeSpinLock<T> {
+    locked: AtomicBool,
+    owner: AtomicUsize,
+    // 创建锁的位置，lockdep 用它区分锁的类
+    #[cfg(feature = "lockdep")]
+    location: &'static Location<'static>,
+    data: UnsafeCell<T>,
+}
+
+unsafe impl<T: Send> Sync for IrqSafeSpinLock<T> {}
+unsafe impl<T: Send> Send for IrqSafeSpinLock<T> {}
+
+pub struct IrqSafeSpinLockGuard<'a, T> {
+    lock: &'a IrqSafeSpinLock<T>,
+}
+
+impl<T> IrqSafeSpinLock<T> {
+    #[track_caller]
+    pub const fn new(data: T) -> Self {
+        Self {
+            locked: AtomicBool::new(false),
+            owner: AtomicUsize::new(NO_OWNER),
+            #[cfg(feature = "lockdep")]
+            location: Location::caller(),
+            data: UnsafeCell::new(data),
+        }
+    }
+
+    pub fn lock(&self) -> IrqSafeSpinLockGuard<'_, T> {
+        cpu::push_off();
+        let hart = cpu::hart_id();
+        // 只有当前 hart 自己会把 owner 设成 hart，读到它说明是当前 hart 重复加锁
+        if self.owner.load(Ordering::Relaxed) == hart {
+            panic!("IrqSafeSpinLock: recursive lock on hart {}", hart);
+        }
+        #[cfg(feature = "lockdep")]
+        super::lockdep::acquire(self.location);
+        while self
+            .locked
+            .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            spin_loop();
+        }
+        self.owner.store(hart, Ordering::Relaxed);
+        IrqSafeSpinLockGuard { lock: self }
+    }
+
+    /// 锁被占用时返回 None，用于出错报告等不能等待的场合
+    pub fn try_lock(&self) -> Option<IrqSafeSpinLockGuard<'_, T>> {
+        cpu::push_off();
+        if self
+            .locked
+            .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            cpu::pop_off();
+            return None;
+        }
+        self.owner.store(cpu::hart_id(), Ordering::Relaxed);
+        #[cfg(feature = "lockdep")]
+        super::lockdep::acquire(self.location);
+        Some(IrqSafeSpinLockGuard { lock: self })
+    }
+}
+
+impl<T> Deref for IrqSafeSpinLockGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<T> DerefMut for IrqSafeSpinLockGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.lock.data.get() }
+    }
+}
+
+impl<T> Drop for IrqSafeSpinLockGuard<'_, T> {
+    fn drop(&mut self) {
+        // 先释放锁再恢复中断
+        #[cfg(feature = "lockdep")]
+        super::lockdep::release(self.lock.location);
+        self.lock.owner.store(NO_OWNER, Ordering::Relaxed);
+        self.lock.locked.store(false, Ordering::Release);
+        cpu::pop_off();
+    }
+}
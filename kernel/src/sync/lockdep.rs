@@ -0,0 +1,123 @@
+use crate::backtrace;
+use crate::config::MAX_HARTS;
+use crate::cpu;
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::vec::Vec;
+use core::panic::Location;
+use spin::Mutex;
+
+// 锁依赖检查 (lockdep)，只在启用 lockdep feature 时编译，用来在真正死锁之前发现加锁顺序的问题
+// 在同一处代码创建的锁属于同一个类 (例如所有 Semaphore 中的等待队列)，第一次加锁时分配编号
+// 持有 A 类的锁时获取 B 类的锁，记下依赖 A -> B 和当时的调用栈；
+// 之后如果出现了 B -> A，两条路径并发执行时可能互相等待 (ABBA 死锁)，打印两次加锁的调用栈
+// 此外 __switch 时当前 hart 不应持有任何自旋锁，否则切换到的任务再加锁就会死锁
+// 这里自己使用的 spin::Mutex 不参与检查；调用者已经关闭了中断
+
+// 每次加锁保存多少层调用栈
+const TRACE_DEPTH: usize = 16;
+
+type Class = usize;
+
+#[derive(Clone, Copy)]
+struct Trace {
+    frames: [usize; TRACE_DEPTH],
+    len: usize,
+}
+
+impl Trace {
+    fn capture() -> Self {
+        let mut frames = [0; TRACE_DEPTH];
+        let len = backtrace::capture(&mut frames);
+        Self { frames, len }
+    }
+
+    fn print(&self) {
+        backtrace::print_frames(&self.frames[..self.len]);
+    }
+}
+
+struct HeldLock {
+    class: Class,
+    trace: Trace, // 加锁时的调用栈
+}
+
+struct Graph {
+    // 类的编号 -> 创建锁的位置
+    classes: Vec<&'static Location<'static>>,
+    // 依赖 (A, B) 第一次出现时获取 B 的调用栈
+    edges: BTreeMap<(Class, Class), Trace>,
+    // 已经报告过的依赖，每对只报告一次
+    reported: BTreeSet<(Class, Class)>,
+}
+
+impl Graph {
+    fn class_of(&mut self, location: &'static Location<'static>) -> Class {
+        match self.classes.iter().position(|&l| l == location) {
+            Some(class) => class,
+            None => {
+                self.classes.push(location);
+                self.classes.len() - 1
+            }
+        }
+    }
+}
+
+static GRAPH: Mutex<Graph> = Mutex::new(Graph {
+    classes: Vec::new(),
+    edges: BTreeMap::new(),
+    reported: BTreeSet::new(),
+});
+
+// 每个 hart 当前持有的锁，按加锁的顺序排列
+static HELD: [Mutex<Vec<HeldLock>>; MAX_HARTS] = [const { Mutex::new(Vec::new()) }; MAX_HARTS];
+
+/// 准备获取 location 处创建的锁，在真正等待之前调用，这样死锁之前就能报告
+pub fn acquire(location: &'static Location<'static>) {
+    let trace = Trace::capture();
+    let mut held = HELD[cpu::hart_id()].lock();
+    let mut graph = GRAPH.lock();
+    let class = graph.class_of(location);
+    for prev in held.iter() {
+        // 同一类的不同实例互相嵌套无法判断顺序，不检查
+        if prev.class == class {
+            continue;
+        }
+        if let Some(reverse) = graph.edges.get(&(class, prev.class)).copied() {
+            if graph.reported.insert((prev.class, class)) {
+                let (a, b) = (graph.classes[prev.class], location);
+                error!("lockdep: possible ABBA deadlock between {} and {}", a, b);
+                println!("acquiring {} while holding {}:", b, a);
+                trace.print();
+                println!("previously acquired {} while holding {}:", a, b);
+                reverse.print();
+            }
+        }
+        graph.edges.entry((prev.class, class)).or_insert(trace);
+    }
+    held.push(HeldLock { class, trace });
+}
+
+/// 释放 location 处创建的锁
+pub fn release(location: &'static Location<'static>) {
+    let mut held = HELD[cpu::hart_id()].lock();
+    let class = GRAPH.lock().class_of(location);
+    if let Some(index) = held.iter().rposition(|lock| lock.class == class) {
+        held.remove(index);
+    }
+}
+
+/// 切换任务之前调用，当前 hart 还持有锁时报告
+pub fn check_switch() {
+    let held = HELD[cpu::hart_id()].lock();
+    if held.is_empty() {
+        return;
+    }
+    let graph = GRAPH.lock();
+    error!("lockdep: __switch with {} lock(s) held", held.len());
+    for lock in held.iter() {
+        println!("{} acquired at:", graph.classes[lock.class]);
+        lock.trace.print();
+    }
+    println!("switching at:");
+    Trace::capture().print();
+}
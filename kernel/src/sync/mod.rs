@@ -0,0 +1,18 @@
+// 会睡眠的同步原语：拿不到锁或资源时阻塞当前任务并切换到其他任务，而不是忙等
+// 每个对象有自己的等待队列 (WaitQueue)；被唤醒的任务重新检查条件，拿不到就继续睡
+// 对象内部的状态用 spin::Mutex 保护，阻塞之前必须释放它
+
+mod condvar;
+pub mod futex;
+mod irq_lock;
+#[cfg(feature = "lockdep")]
+pub mod lockdep;
+mod mutex;
+mod semaphore;
+mod wait_queue;
+
+pub use condvar::Condvar;
+pub use irq_lock::IrqSafeSpinLock;
+pub use mutex::Mutex;
+pub use semaphore::Semaphore;
+pub use wait_queue::WaitQueue;
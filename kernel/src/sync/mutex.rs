@@ -0,0 +1,46 @@
+use super::WaitQueue;
+use crate::task::manager::current_task_id;
+
+/// 会睡眠的互斥锁，记录持有者，只有持有者才能解锁
+#[derive(Default)]
+pub struct Mutex {
+    owner: spin::Mutex<Option<usize>>, // 持有锁的任务，None 表示没有上锁
+    wait_queue: WaitQueue,
+}
+
+impl Mutex {
+    /// 上锁，锁被其他任务持有时阻塞。当前任务已经持有锁时返回 false
+    pub fn lock(&self) -> bool {
+        let current = current_task_id();
+        if self.is_owned_by_current() {
+            return false;
+        }
+        self.wait_queue.wait_until(|| {
+            let mut owner = self.owner.lock();
+            if owner.is_none() {
+                *owner = Some(current);
+                true
+            } else {
+                false
+            }
+        });
+        true
+    }
+
+    /// 解锁并唤醒一个等待的任务。当前任务没有持有锁时返回 false
+    pub fn unlock(&self) -> bool {
+        let mut owner = self.owner.lock();
+        if *owner != Some(current_task_id()) {
+            return false;
+        }
+        *owner = None;
+        drop(owner);
+        self.wait_queue.notify_one();
+        true
+    }
+
+    /// 当前任务是否持有锁
+    pub fn is_owned_by_current(&self) -> bool {
+        *self.owner.lock() == Some(current_task_id())
+    }
+}
@@ -0,0 +1,35 @@
+use super::WaitQueue;
+
+/// 计数信号量
+pub struct Semaphore {
+    count: spin::Mutex<usize>,
+    wait_queue: WaitQueue,
+}
+
+impl Semaphore {
+    pub fn new(count: usize) -> Self {
+        Self {
+            count: spin::Mutex::new(count),
+            wait_queue: WaitQueue::new(),
+        }
+    }
+
+    /// V 操作：计数加一，唤醒一个等待的任务
+    pub fn up(&self) {
+        *self.count.lock() += 1;
+        self.wait_queue.notify_one();
+    }
+
+    /// P 操作：计数为 0 时阻塞，直到其他任务调用 up
+    pub fn down(&self) {
+        self.wait_queue.wait_until(|| {
+            let mut count = self.count.lock();
+            if *count > 0 {
+                *count -= 1;
+                true
+            } else {
+                false
+            }
+        });
+    }
+}
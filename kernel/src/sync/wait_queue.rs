@@ -0,0 +1,88 @@
+use super::IrqSafeSpinLock;
+use crate::task::manager::{block_current_and_run_next, current_task_id, wakeup_task};
+use crate::timer;
+use alloc::collections::VecDeque;
+
+/// 等待队列：保存阻塞在某个事件上的任务，事件发生时由 notify_one / notify_all 唤醒
+/// 调用者先在自己的锁下检查条件，释放锁后再 wait
+/// 内核中的中断是关闭的，释放锁和进入队列之间不会有其他任务或中断处理函数插进来，所以不会丢失唤醒
+pub struct WaitQueue {
+    queue: IrqSafeSpinLock<VecDeque<usize>>,
+}
+
+impl Default for WaitQueue {
+    #[track_caller]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WaitQueue {
+    #[track_caller]
+    pub const fn new() -> Self {
+        Self {
+            queue: IrqSafeSpinLock::new(VecDeque::new()),
+        }
+    }
+
+    /// 阻塞当前任务直到被唤醒，返回是否是被 notify 唤醒的
+    /// 被其他原因 (如超时) 唤醒时把自己移出队列，免得之后的 notify 落空
+    fn block(&self) -> bool {
+        let current = current_task_id();
+        self.queue.lock().push_back(current);
+        block_current_and_run_next();
+        let mut queue = self.queue.lock();
+        match queue.iter().position(|&id| id == current) {
+            Some(index) => {
+                queue.remove(index);
+                false
+            }
+            None => true,
+        }
+    }
+
+    /// 阻塞当前任务，直到被唤醒。返回时等待的条件不一定成立，调用者要重新检查
+    pub fn wait(&self) {
+        self.block();
+    }
+
+    /// 阻塞当前任务，直到 cond 返回 true。cond 在被唤醒后重新调用，可以顺便获取资源
+    pub fn wait_until(&self, mut cond: impl FnMut() -> bool) {
+        while !cond() {
+            self.block();
+        }
+    }
+
+    /// 阻塞当前任务，直到被唤醒或者单调时钟到达 deadline (纳秒)
+    /// 返回 false 表示超时
+    pub fn wait_timeout(&self, deadline: u64) -> bool {
+        if timer::monotonic_ns() >= deadline {
+            return false;
+        }
+        let timer = timer::wakeup_at(deadline, current_task_id());
+        let notified = self.block();
+        timer::cancel_timer(timer);
+        notified
+    }
+
+    /// 唤醒最早进入队列的一个任务，返回是否有任务被唤醒
+    pub fn notify_one(&self) -> bool {
+        match self.queue.lock().pop_front() {
+            Some(task_id) => {
+                wakeup_task(task_id);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 唤醒所有等待的任务，返回唤醒的任务数
+    pub fn notify_all(&self) -> usize {
+        let mut queue = self.queue.lock();
+        let count = queue.len();
+        for task_id in queue.drain(..) {
+            wakeup_task(task_id);
+        }
+        count
+    }
+}
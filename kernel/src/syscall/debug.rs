@@ -0,0 +1,24 @@
+use super::errno::EINVAL;
+use crate::mm::page_table::PageTable;
+use crate::task::manager::{current_user_token, with_current_process};
+
+// sys_dump_vm 的参数：要打印的内容，可以同时指定多个
+const DUMP_AREAS: usize = 1 << 0; // 地址空间中的各个区域
+const DUMP_PAGE_TABLE: usize = 1 << 1; // 页表中所有有效的页表项
+
+/// 调试用：把当前进程的地址空间打印到内核控制台
+pub fn sys_dump_vm(what: usize) -> isize {
+    if what == 0 || what & !(DUMP_AREAS | DUMP_PAGE_TABLE) != 0 {
+        return -EINVAL;
+    }
+    if what & DUMP_AREAS != 0 {
+        with_current_process(|process| process.memory_set.dump());
+    }
+    if what & DUMP_PAGE_TABLE != 0 {
+        // 通过 satp 得到页表的视图，打印期间不需要持有 TASK_MANAGER 的锁
+        if let Some(page_table) = PageTable::from_token(current_user_token()) {
+            page_table.dump();
+        }
+    }
+    0
+}
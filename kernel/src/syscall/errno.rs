@@ -0,0 +1,16 @@
+// 系统调用出错时返回负的错误码，取值与 Linux 相同
+
+pub const EPERM: isize = 1; // 操作不允许
+pub const ENOENT: isize = 2; // 文件不存在
+pub const ESRCH: isize = 3; // 线程不存在
+pub const EINTR: isize = 4; // 被中断
+pub const EBADF: isize = 9; // 无效的文件描述符
+pub const EAGAIN: isize = 11; // 资源暂时不可用，可以重试
+pub const EFAULT: isize = 14; // 无效的用户地址
+pub const EINVAL: isize = 22; // 无效的参数
+pub const ENOTTY: isize = 25; // 不是终端
+pub const EPIPE: isize = 32; // 要读的内核日志已被覆盖
+pub const EDEADLK: isize = 35; // 会导致死锁
+pub const ENAMETOOLONG: isize = 36; // 路径太长
+pub const ENOSYS: isize = 38; // 不支持的系统调用
+pub const ETIMEDOUT: isize = 110; // 超时
@@ -0,0 +1,141 @@
+use super::errno::{EBADF, EINVAL, ENOENT, ENOTTY};
+use super::uaccess::{copy_to_user, read_user_cstr, UserPtr, UserSlice};
+use crate::fs::{self, File};
+use crate::task::manager::with_current_process;
+use crate::tty;
+use alloc::sync::Arc;
+use alloc::vec;
+
+// 一次 read 最多读取的字节数，读到的数据先放在内核缓冲区中
+const READ_CHUNK: usize = 4096;
+// 路径的最大长度 (包括末尾的 0)
+const PATH_MAX: usize = 256;
+
+// ioctl 请求：读取 / 设置终端属性
+const TCGETS: usize = 0x5401;
+const TCSETS: usize = 0x5402;
+
+/// 与 Linux 的 struct termios 布局相同，内核只关心 c_lflag
+#[derive(Debug, Default, Clone, Copy)]
+#[repr(C)]
+struct Termios {
+    c_iflag: u32,
+    c_oflag: u32,
+    c_cflag: u32,
+    c_lflag: u32,
+    c_line: u8,
+    c_cc: [u8; 19],
+}
+
+// c_cc 中各控制字符的下标
+const VINTR: usize = 0;
+const VERASE: usize = 2;
+const VKILL: usize = 3;
+const VEOF: usize = 4;
+
+/// 当前任务中 fd 对应的文件
+fn get_file(fd: usize) -> Option<Arc<dyn File>> {
+    with_current_process(|process| process.fd_table.get(fd).cloned().flatten())
+}
+
+pub fn sys_write(fd: usize, buf: *const u8, len: usize) -> isize {
+    let Some(file) = get_file(fd).filter(|file| file.writable()) else {
+        return -EBADF;
+    };
+    let buffers = match UserSlice::new(buf as usize, len).readable() {
+        Ok(buffers) => buffers,
+        Err(err) => return err,
+    };
+    let mut written = 0;
+    for buffer in buffers {
+        let ret = file.write(buffer);
+        if ret < 0 {
+            return ret;
+        }
+        written += ret;
+    }
+    written
+}
+
+/// 读取可能阻塞 (例如终端没有输入)，阻塞期间不能持有任何锁
+pub fn sys_read(fd: usize, buf: *mut u8, len: usize) -> isize {
+    let Some(file) = get_file(fd).filter(|file| file.readable()) else {
+        return -EBADF;
+    };
+    if len == 0 {
+        return 0;
+    }
+    // 先检查缓冲区是否有效，避免读走了输入却无处存放
+    if let Err(err) = UserSlice::new(buf as usize, len).writable() {
+        return err;
+    }
+    let mut data = vec![0; len.min(READ_CHUNK)];
+    let ret = file.read(&mut data);
+    if ret <= 0 {
+        return ret;
+    }
+    match copy_to_user(buf as usize, &data[..ret as usize]) {
+        Ok(()) => ret,
+        Err(err) => err,
+    }
+}
+
+/// 目前只支持对终端读取和设置 termios
+pub fn sys_ioctl(fd: usize, request: usize, arg: usize) -> isize {
+    let Some(file) = get_file(fd) else {
+        return -EBADF;
+    };
+    if !file.is_tty() {
+        return -ENOTTY;
+    }
+    let user_termios = UserPtr::from(arg as *mut Termios);
+    match request {
+        TCGETS => {
+            let mut termios = Termios {
+                c_lflag: tty::lflag(),
+                ..Termios::default()
+            };
+            termios.c_cc[VINTR] = 0x03;
+            termios.c_cc[VERASE] = 0x7f;
+            termios.c_cc[VKILL] = 0x15;
+            termios.c_cc[VEOF] = 0x04;
+            match user_termios.write(termios) {
+                Ok(()) => 0,
+                Err(err) => err,
+            }
+        }
+        TCSETS => match user_termios.read() {
+            Ok(termios) => {
+                tty::set_lflag(termios.c_lflag);
+                0
+            }
+            Err(err) => err,
+        },
+        _ => -EINVAL,
+    }
+}
+
+/// 打开文件，只支持绝对路径，dirfd 被忽略
+pub fn sys_openat(_dirfd: isize, path: *const u8, flags: u32) -> isize {
+    let path = match read_user_cstr(path as usize, PATH_MAX) {
+        Ok(path) => path,
+        Err(err) => return err,
+    };
+    let Some(file) = fs::open(&path, flags) else {
+        return -ENOENT;
+    };
+    with_current_process(|process| {
+        let fd = process.alloc_fd();
+        process.fd_table[fd] = Some(file);
+        fd as isize
+    })
+}
+
+pub fn sys_close(fd: usize) -> isize {
+    with_current_process(
+        |process| match process.fd_table.get_mut(fd).and_then(Option::take) {
+            Some(_) => 0,
+            None => -EBADF,
+        },
+    )
+}
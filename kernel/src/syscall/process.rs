@@ -0,0 +1,10 @@
+use crate::task::manager::{exit_current_and_run_next, suspend_current_and_run_next};
+
+pub fn sys_exit(exit_code: i32) -> ! {
+    exit_current_and_run_next(exit_code)
+}
+
+pub fn sys_yield() -> isize {
+    suspend_current_and_run_next();
+    0
+}
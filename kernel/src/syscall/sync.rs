@@ -0,0 +1,176 @@
+use super::errno::{EAGAIN, EDEADLK, EINVAL, ENOSYS, EPERM, ETIMEDOUT};
+use super::time::{read_user_timespec, TimeSpec};
+use super::uaccess::translate_user;
+use crate::mm::page_table::PTEFlags;
+use crate::sync::futex::{self, FutexWait};
+use crate::sync::{Condvar, Mutex, Semaphore};
+use crate::task::manager::with_current_process;
+use crate::timer;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+
+// futex 操作，取值与 Linux 相同
+const FUTEX_WAIT: usize = 0;
+const FUTEX_WAKE: usize = 1;
+const FUTEX_REQUEUE: usize = 3;
+const FUTEX_CMP_REQUEUE: usize = 4;
+// 只在一个进程中使用；futex 总是以物理地址为键，所以这个标志不影响结果
+const FUTEX_PRIVATE_FLAG: usize = 128;
+
+// 同步对象保存在当前进程的表中，进程中的线程共享，系统调用使用表中的下标作为编号
+// 阻塞之前要先把对象从表中取出来 (Arc)，不能持有 TASK_MANAGER 的锁睡眠
+
+/// 把对象放进最小的空闲位置，返回它的编号
+fn insert<T>(list: &mut Vec<Option<Arc<T>>>, object: T) -> isize {
+    let id = match list.iter().position(Option::is_none) {
+        Some(id) => id,
+        None => {
+            list.push(None);
+            list.len() - 1
+        }
+    };
+    list[id] = Some(Arc::new(object));
+    id as isize
+}
+
+fn get_mutex(id: usize) -> Option<Arc<Mutex>> {
+    with_current_process(|process| process.mutex_list.get(id).cloned().flatten())
+}
+
+fn get_semaphore(id: usize) -> Option<Arc<Semaphore>> {
+    with_current_process(|process| process.semaphore_list.get(id).cloned().flatten())
+}
+
+fn get_condvar(id: usize) -> Option<Arc<Condvar>> {
+    with_current_process(|process| process.condvar_list.get(id).cloned().flatten())
+}
+
+pub fn sys_mutex_create() -> isize {
+    with_current_process(|process| insert(&mut process.mutex_list, Mutex::default()))
+}
+
+/// 已经持有锁时再次上锁会死锁，返回 EDEADLK
+pub fn sys_mutex_lock(id: usize) -> isize {
+    let Some(mutex) = get_mutex(id) else {
+        return -EINVAL;
+    };
+    if mutex.lock() {
+        0
+    } else {
+        -EDEADLK
+    }
+}
+
+/// 只有持有锁的任务才能解锁，否则返回 EPERM
+pub fn sys_mutex_unlock(id: usize) -> isize {
+    let Some(mutex) = get_mutex(id) else {
+        return -EINVAL;
+    };
+    if mutex.unlock() {
+        0
+    } else {
+        -EPERM
+    }
+}
+
+pub fn sys_semaphore_create(count: usize) -> isize {
+    with_current_process(|process| insert(&mut process.semaphore_list, Semaphore::new(count)))
+}
+
+pub fn sys_semaphore_up(id: usize) -> isize {
+    let Some(semaphore) = get_semaphore(id) else {
+        return -EINVAL;
+    };
+    semaphore.up();
+    0
+}
+
+pub fn sys_semaphore_down(id: usize) -> isize {
+    let Some(semaphore) = get_semaphore(id) else {
+        return -EINVAL;
+    };
+    semaphore.down();
+    0
+}
+
+pub fn sys_condvar_create() -> isize {
+    with_current_process(|process| insert(&mut process.condvar_list, Condvar::default()))
+}
+
+pub fn sys_condvar_signal(id: usize) -> isize {
+    let Some(condvar) = get_condvar(id) else {
+        return -EINVAL;
+    };
+    condvar.signal();
+    0
+}
+
+/// 调用者必须持有 mutex_id 对应的锁，否则返回 EPERM
+pub fn sys_condvar_wait(id: usize, mutex_id: usize) -> isize {
+    let (Some(condvar), Some(mutex)) = (get_condvar(id), get_mutex(mutex_id)) else {
+        return -EINVAL;
+    };
+    if !mutex.is_owned_by_current() {
+        return -EPERM;
+    }
+    condvar.wait(&mutex);
+    0
+}
+
+/// 用户地址 uaddr 处的 futex 变量的物理地址，不同进程映射同一物理页时得到相同的键
+fn futex_key(uaddr: usize) -> Result<usize, isize> {
+    if uaddr % 4 != 0 {
+        return Err(-EINVAL);
+    }
+    Ok(translate_user(uaddr, PTEFlags::R)?.0)
+}
+
+/// 参数的含义与 Linux 相同：
+/// FUTEX_WAIT：*uaddr == val 时睡眠，timeout 为相对时间 (可以为空)，超时返回 ETIMEDOUT，值不相等返回 EAGAIN
+/// FUTEX_WAKE：唤醒最多 val 个等待 uaddr 的任务，返回唤醒的个数
+/// FUTEX_REQUEUE / FUTEX_CMP_REQUEUE：唤醒最多 val 个，再把最多 val2 个移到 uaddr2 上等待，
+/// CMP 版本先检查 *uaddr == val3，返回唤醒和移动的总数
+pub fn sys_futex(
+    uaddr: usize,
+    op: usize,
+    val: u32,
+    timeout_or_val2: usize,
+    uaddr2: usize,
+    val3: u32,
+) -> isize {
+    let key = match futex_key(uaddr) {
+        Ok(key) => key,
+        Err(err) => return err,
+    };
+    match op & !FUTEX_PRIVATE_FLAG {
+        FUTEX_WAIT => {
+            let timeout = timeout_or_val2 as *const TimeSpec;
+            let deadline = if timeout.is_null() {
+                None
+            } else {
+                match read_user_timespec(timeout) {
+                    Ok(ns) => Some(timer::monotonic_ns().saturating_add(ns)),
+                    Err(err) => return err,
+                }
+            };
+            match futex::wait(key, val, deadline) {
+                FutexWait::Woken => 0,
+                FutexWait::Mismatch => -EAGAIN,
+                FutexWait::TimedOut => -ETIMEDOUT,
+            }
+        }
+        FUTEX_WAKE => futex::wake(key, val as usize) as isize,
+        op @ (FUTEX_REQUEUE | FUTEX_CMP_REQUEUE) => {
+            let target = match futex_key(uaddr2) {
+                Ok(target) => target,
+                Err(err) => return err,
+            };
+            let expected = (op == FUTEX_CMP_REQUEUE).then_some(val3);
+            match futex::requeue(key, target, val as usize, timeout_or_val2, expected) {
+                Some(count) => count as isize,
+                None => -EAGAIN,
+            }
+        }
+        _ => -ENOSYS,
+    }
+}
@@ -0,0 +1,64 @@
+use super::errno::{EFAULT, EINVAL};
+use super::uaccess::copy_to_user;
+use crate::kmsg;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+// syslog 的操作，取值与 Linux 相同
+const SYSLOG_ACTION_READ_ALL: usize = 3; // 读取缓冲区中的消息，不影响之后的读取
+const SYSLOG_ACTION_READ_CLEAR: usize = 4; // 读取之后清空
+const SYSLOG_ACTION_CLEAR: usize = 5; // 清空
+const SYSLOG_ACTION_SIZE_BUFFER: usize = 10; // 缓冲区的总大小
+
+/// 把上次清空之后的消息格式化成 "<级别>[时间戳] 消息\n"，只保留最后放得下 len 字节的那些
+fn read_all(len: usize) -> Vec<u8> {
+    let mut lines = Vec::new();
+    let mut seq = kmsg::clear_seq();
+    while let Some(record) = kmsg::read(seq) {
+        seq = record.seq + 1;
+        lines.push(format!(
+            "<{}>[{:>5}.{:06}] {}\n",
+            record.level,
+            record.timestamp_us / 1_000_000,
+            record.timestamp_us % 1_000_000,
+            String::from_utf8_lossy(&record.text)
+        ));
+    }
+    // 和 Linux 一样，缓冲区放不下时丢弃最早的消息
+    let mut total = 0;
+    let keep = lines
+        .iter()
+        .rev()
+        .take_while(|line| {
+            total += line.len();
+            total <= len
+        })
+        .count();
+    lines[lines.len() - keep..].concat().into_bytes()
+}
+
+/// 读取或清空内核日志缓冲区
+pub fn sys_syslog(action: usize, buf: *mut u8, len: usize) -> isize {
+    match action {
+        SYSLOG_ACTION_READ_ALL | SYSLOG_ACTION_READ_CLEAR => {
+            if buf.is_null() {
+                return -EFAULT;
+            }
+            let data = read_all(len);
+            if let Err(err) = copy_to_user(buf as usize, &data) {
+                return err;
+            }
+            if action == SYSLOG_ACTION_READ_CLEAR {
+                kmsg::clear();
+            }
+            data.len() as isize
+        }
+        SYSLOG_ACTION_CLEAR => {
+            kmsg::clear();
+            0
+        }
+        SYSLOG_ACTION_SIZE_BUFFER => kmsg::buffer_size() as isize,
+        _ => -EINVAL,
+    }
+}
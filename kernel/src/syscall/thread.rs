@@ -0,0 +1,28 @@
+use super::uaccess::UserPtr;
+use crate::task::manager::{current_tid, thread_create, waittid};
+
+/// 在当前进程中创建线程，从 entry 开始执行，a0 为 arg，返回新线程的 tid
+/// 线程函数不能返回，结束时要调用 exit
+pub fn sys_thread_create(entry: usize, arg: usize) -> isize {
+    thread_create(entry, arg) as isize
+}
+
+pub fn sys_gettid() -> isize {
+    current_tid() as isize
+}
+
+/// 等待线程 tid 退出并回收它，exit_code 不为空时把退出码写到那里
+pub fn sys_waittid(tid: usize, exit_code: *mut i32) -> isize {
+    let code = match waittid(tid) {
+        Ok(code) => code,
+        Err(err) => return err,
+    };
+    let exit_code = UserPtr::from(exit_code);
+    if exit_code.is_null() {
+        return 0;
+    }
+    match exit_code.write(code) {
+        Ok(()) => 0,
+        Err(err) => err,
+    }
+}
@@ -0,0 +1,79 @@
+use super::errno::EINVAL;
+use super::uaccess::UserPtr;
+use crate::drivers::rtc;
+use crate::timer::{self, NSEC_PER_SEC};
+
+// 时钟，取值与 Linux 相同
+const CLOCK_REALTIME: usize = 0;
+const CLOCK_MONOTONIC: usize = 1;
+const CLOCK_BOOTTIME: usize = 7; // 内核不会挂起，和 CLOCK_MONOTONIC 相同
+
+/// 与 Linux 的 struct timespec 布局相同
+#[derive(Debug, Default, Clone, Copy)]
+#[repr(C)]
+pub struct TimeSpec {
+    pub tv_sec: i64,
+    pub tv_nsec: i64,
+}
+
+impl TimeSpec {
+    fn from_ns(ns: u64) -> Self {
+        Self {
+            tv_sec: (ns / NSEC_PER_SEC) as i64,
+            tv_nsec: (ns % NSEC_PER_SEC) as i64,
+        }
+    }
+
+    /// 换算成纳秒，取值不合法时返回 None
+    fn to_ns(self) -> Option<u64> {
+        if self.tv_sec < 0 || !(0..NSEC_PER_SEC as i64).contains(&self.tv_nsec) {
+            return None;
+        }
+        (self.tv_sec as u64)
+            .checked_mul(NSEC_PER_SEC)?
+            .checked_add(self.tv_nsec as u64)
+    }
+}
+
+/// 从用户地址空间读取一个 TimeSpec 并换算成纳秒
+pub(super) fn read_user_timespec(ptr: *const TimeSpec) -> Result<u64, isize> {
+    UserPtr::from(ptr).read()?.to_ns().ok_or(-EINVAL)
+}
+
+pub fn sys_clock_gettime(clock_id: usize, tp: *mut TimeSpec) -> isize {
+    let ns = match clock_id {
+        CLOCK_REALTIME => timer::realtime_ns(),
+        CLOCK_MONOTONIC | CLOCK_BOOTTIME => timer::monotonic_ns(),
+        _ => return -EINVAL,
+    };
+    match UserPtr::from(tp).write(TimeSpec::from_ns(ns)) {
+        Ok(()) => 0,
+        Err(err) => err,
+    }
+}
+
+/// 只能设置 CLOCK_REALTIME，同时写回 RTC，重启之后仍然有效
+pub fn sys_clock_settime(clock_id: usize, tp: *const TimeSpec) -> isize {
+    if clock_id != CLOCK_REALTIME {
+        return -EINVAL;
+    }
+    let ns = match read_user_timespec(tp) {
+        Ok(ns) => ns,
+        Err(err) => return err,
+    };
+    timer::set_realtime_ns(ns);
+    if let Some(rtc) = rtc::get() {
+        rtc.set_time_ns(ns);
+    }
+    0
+}
+
+/// 睡眠期间任务被阻塞；没有信号打断睡眠，所以不会写 rem
+pub fn sys_nanosleep(req: *const TimeSpec, _rem: *mut TimeSpec) -> isize {
+    let ns = match read_user_timespec(req) {
+        Ok(ns) => ns,
+        Err(err) => return err,
+    };
+    timer::sleep_until(timer::monotonic_ns().saturating_add(ns));
+    0
+}
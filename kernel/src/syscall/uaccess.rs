@@ -0,0 +1,164 @@
+// 访问用户地址空间：先用当前进程的页表把用户地址翻译成物理地址，再通过恒等映射访问
+// 通过 satp 得到页表的视图，查询时不需要持有 TASK_MANAGER 的锁
+// 每一页都要检查 U 标志和需要的读写权限，地址无效时返回 EFAULT，不会让内核出错
+// 用户缓冲区在物理上不一定连续，跨页的缓冲区按页分段访问
+
+use super::errno::{EFAULT, EINVAL, ENAMETOOLONG};
+use crate::mm::address::{PhysAddr, VirtAddr, PAGE_SIZE};
+use crate::mm::page_table::{PTEFlags, PageTable};
+use crate::task::manager::current_user_token;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+use core::mem::{size_of, MaybeUninit};
+
+fn current_page_table() -> Result<PageTable, isize> {
+    PageTable::from_token(current_user_token()).ok_or(-EFAULT)
+}
+
+/// 用户地址 va 对应的物理地址，va 所在的页必须是用户态可以按 access 访问的
+pub(super) fn translate_user(va: usize, access: PTEFlags) -> Result<PhysAddr, isize> {
+    let va = VirtAddr(va);
+    let pte = current_page_table()?
+        .translate_user(va.floor(), access)
+        .ok_or(-EFAULT)?;
+    Ok(PhysAddr(PhysAddr::from(pte.ppn()).0 + va.page_offset()))
+}
+
+/// 用户地址空间中的一段缓冲区 [addr, addr + len)
+#[derive(Debug, Clone, Copy)]
+pub(super) struct UserSlice {
+    addr: usize,
+    len: usize,
+}
+
+impl UserSlice {
+    pub fn new(addr: usize, len: usize) -> Self {
+        Self { addr, len }
+    }
+
+    /// 转换成若干段内核可以直接访问的切片 (每一页一段)，access 是需要的权限
+    fn buffers(&self, access: PTEFlags) -> Result<Vec<&'static mut [u8]>, isize> {
+        current_page_table()?
+            .translated_byte_buffer(self.addr, self.len, access)
+            .ok_or(-EFAULT)
+    }
+
+    /// 用户态可读的缓冲区，按页分段
+    pub fn readable(&self) -> Result<Vec<&'static [u8]>, isize> {
+        Ok(self
+            .buffers(PTEFlags::R)?
+            .into_iter()
+            .map(|buffer| &*buffer)
+            .collect())
+    }
+
+    /// 用户态可写的缓冲区，按页分段
+    pub fn writable(&self) -> Result<Vec<&'static mut [u8]>, isize> {
+        self.buffers(PTEFlags::W)
+    }
+
+    /// 把整个缓冲区复制到 data 中，data 的长度必须和缓冲区相同
+    pub fn read(&self, data: &mut [u8]) -> Result<(), isize> {
+        assert_eq!(data.len(), self.len);
+        let mut offset = 0;
+        for buffer in self.readable()? {
+            data[offset..offset + buffer.len()].copy_from_slice(buffer);
+            offset += buffer.len();
+        }
+        Ok(())
+    }
+
+    /// 把 data 复制到整个缓冲区中，data 的长度必须和缓冲区相同
+    pub fn write(&self, data: &[u8]) -> Result<(), isize> {
+        assert_eq!(data.len(), self.len);
+        let mut offset = 0;
+        for buffer in self.writable()? {
+            buffer.copy_from_slice(&data[offset..offset + buffer.len()]);
+            offset += buffer.len();
+        }
+        Ok(())
+    }
+}
+
+/// 从用户地址 src 处读出 dst.len() 个字节
+pub(super) fn copy_from_user(dst: &mut [u8], src: usize) -> Result<(), isize> {
+    UserSlice::new(src, dst.len()).read(dst)
+}
+
+/// 把 src 复制到用户地址 dst 处
+pub(super) fn copy_to_user(dst: usize, src: &[u8]) -> Result<(), isize> {
+    UserSlice::new(dst, src.len()).write(src)
+}
+
+/// 指向用户地址空间中一个 T 类型对象的指针，按字节复制，不要求对齐
+/// T 必须是任意字节内容都合法的类型 (整数，或者只包含整数的 repr(C) 结构体)
+#[derive(Debug)]
+pub(super) struct UserPtr<T> {
+    addr: usize,
+    _marker: PhantomData<*mut T>,
+}
+
+impl<T> Clone for UserPtr<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for UserPtr<T> {}
+
+impl<T> From<*const T> for UserPtr<T> {
+    fn from(ptr: *const T) -> Self {
+        Self {
+            addr: ptr as usize,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T> From<*mut T> for UserPtr<T> {
+    fn from(ptr: *mut T) -> Self {
+        Self::from(ptr as *const T)
+    }
+}
+
+impl<T: Copy> UserPtr<T> {
+    pub fn is_null(&self) -> bool {
+        self.addr == 0
+    }
+
+    pub fn read(&self) -> Result<T, isize> {
+        let mut value = MaybeUninit::<T>::zeroed();
+        let bytes = unsafe {
+            core::slice::from_raw_parts_mut(value.as_mut_ptr() as *mut u8, size_of::<T>())
+        };
+        copy_from_user(bytes, self.addr)?;
+        Ok(unsafe { value.assume_init() })
+    }
+
+    pub fn write(&self, value: T) -> Result<(), isize> {
+        let bytes =
+            unsafe { core::slice::from_raw_parts(&value as *const T as *const u8, size_of::<T>()) };
+        copy_to_user(self.addr, bytes)
+    }
+}
+
+/// 读取用户地址空间中以 0 结尾的字符串，max_len 包括末尾的 0
+/// 超过 max_len 还没有遇到 0 时返回 ENAMETOOLONG，不是 UTF-8 时返回 EINVAL
+pub(super) fn read_user_cstr(ptr: usize, max_len: usize) -> Result<String, isize> {
+    let mut bytes = Vec::new();
+    let mut va = ptr;
+    while bytes.len() < max_len {
+        // 每次读到页末尾，避免访问下一个可能没有映射的页
+        let chunk = (PAGE_SIZE - va % PAGE_SIZE).min(max_len - bytes.len());
+        for buffer in UserSlice::new(va, chunk).readable()? {
+            if let Some(end) = buffer.iter().position(|&b| b == 0) {
+                bytes.extend_from_slice(&buffer[..end]);
+                return String::from_utf8(bytes).map_err(|_| -EINVAL);
+            }
+            bytes.extend_from_slice(buffer);
+        }
+        va += chunk;
+    }
+    Err(-ENAMETOOLONG)
+}
@@ -1,8 +1,16 @@
 use super::context::TaskContext;
+use super::process::ProcessControlBlock;
 use super::task_block::{TaskControlBlock, TaskStatus};
+use crate::config::SCHED_TICK_MS;
+use crate::cpu::wait_for_interrupt;
+use crate::sbi::shutdown;
+use crate::sync::{futex, IrqSafeSpinLock};
+use crate::syscall::errno::{EDEADLK, ESRCH};
+use crate::timer::{self, TimerId};
+use crate::trap::context::TrapContext;
 use alloc::vec::Vec;
 use core::arch::global_asm;
-use lazy_static::lazy_static; // 需要引入 lazy_static 依赖
+use core::sync::atomic::{AtomicBool, Ordering};
 
 global_asm!(include_str!("switch.S"));
 
@@ -10,80 +18,381 @@ extern "C" {
     fn __switch(current_task_cx_ptr: *mut TaskContext, next_task_cx_ptr: *const TaskContext);
 }
 
+// 调度的对象是线程 (任务)，进程只是线程共享的资源
 pub struct TaskManager {
-    inner: Vec<TaskControlBlock>,
+    // 所有线程，下标是线程的全局编号，调度和唤醒都使用它；被回收的线程为 None
+    // 全局编号不会重复使用，过时的唤醒不会落到新线程上
+    inner: Vec<Option<TaskControlBlock>>,
+    // 所有进程，下标就是 pid；被回收的进程为 None
+    processes: Vec<Option<ProcessControlBlock>>,
+    // 已经退出、还没有回收的进程
+    zombies: Vec<usize>,
     current_task: usize,
 }
 
 impl TaskManager {
-    fn find_next_task_cx(&mut self) -> Option<(*mut TaskContext, *const TaskContext)> {
-        if let Some(next) = self.find_next_task() {
-            let current = self.current_task;
-            self.inner[current].task_status = TaskStatus::Ready;
-            self.inner[next].task_status = TaskStatus::Running;
-            self.current_task = next;
-
-            let current_task_cx_ptr = &mut self.inner[current].task_cx as *mut TaskContext;
-            let next_task_cx_ptr = &self.inner[next].task_cx as *const TaskContext;
-
-            Some((current_task_cx_ptr, next_task_cx_ptr))
-        } else {
-            None
-        }
+    fn task(&self, id: usize) -> &TaskControlBlock {
+        self.inner[id].as_ref().unwrap()
+    }
+
+    fn task_mut(&mut self, id: usize) -> &mut TaskControlBlock {
+        self.inner[id].as_mut().unwrap()
     }
 
+    /// 从当前任务的下一个开始轮询，找到一个就绪的任务 (当前任务排在最后)
     fn find_next_task(&self) -> Option<usize> {
         let n = self.inner.len();
-        for i in 1..=n {
-            let next = (self.current_task + i) % n;
-            if self.inner[next].task_status == TaskStatus::Ready {
-                return Some(next);
+        (1..=n).map(|i| (self.current_task + i) % n).find(|&next| {
+            self.inner[next]
+                .as_ref()
+                .is_some_and(|task| task.task_status == TaskStatus::Ready)
+        })
+    }
+
+    /// 是否还有被阻塞、将来可能被唤醒的任务
+    fn has_blocked_task(&self) -> bool {
+        self.inner
+            .iter()
+            .flatten()
+            .any(|task| task.task_status == TaskStatus::Blocked)
+    }
+
+    fn current(&self) -> &TaskControlBlock {
+        self.task(self.current_task)
+    }
+
+    fn process(&self, pid: usize) -> &ProcessControlBlock {
+        self.processes[pid].as_ref().unwrap()
+    }
+
+    fn process_mut(&mut self, pid: usize) -> &mut ProcessControlBlock {
+        self.processes[pid].as_mut().unwrap()
+    }
+
+    fn current_process(&self) -> &ProcessControlBlock {
+        self.process(self.current().pid)
+    }
+
+    fn current_process_mut(&mut self) -> &mut ProcessControlBlock {
+        let pid = self.current().pid;
+        self.process_mut(pid)
+    }
+
+    /// 从 ELF 创建一个进程和它的主线程
+    pub fn add_process(&mut self, name: &'static str, elf_data: &[u8]) {
+        let pid = self.processes.len();
+        let (process, entry) = ProcessControlBlock::new(pid, name, elf_data);
+        self.processes.push(Some(process));
+        self.spawn_thread(pid, entry, 0);
+    }
+
+    /// 回收已经退出的进程：释放所有线程的 TCB 和内核栈，以及进程的地址空间、文件和同步对象
+    /// 当前线程还在自己的内核栈上运行，所以它所在的进程留到切换到别的任务之后再回收
+    fn reap_zombies(&mut self) {
+        let current_pid = self.current().pid;
+        let mut i = 0;
+        while i < self.zombies.len() {
+            let pid = self.zombies[i];
+            if pid == current_pid {
+                i += 1;
+                continue;
+            }
+            self.zombies.swap_remove(i);
+            let process = self.processes[pid].take().unwrap();
+            for &id in process.threads.iter().flatten() {
+                self.inner[id] = None;
             }
+            debug!("Process {} ({}) reaped", pid, process.name);
         }
-        None
     }
 
-    pub fn add_task(&mut self, task: TaskControlBlock) {
-        self.inner.push(task);
+    /// 在进程 pid 中创建一个线程，返回它的 tid
+    fn spawn_thread(&mut self, pid: usize, entry: usize, arg: usize) -> usize {
+        let process = self.processes[pid].as_mut().unwrap();
+        let tid = process.alloc_tid();
+        let task = TaskControlBlock::new(process, tid, entry, arg);
+        process.threads[tid] = Some(self.inner.len());
+        self.inner.push(Some(task));
+        tid
     }
 }
 
 pub fn run_first_task() {
     let mut task_manager = TASK_MANAGER.lock();
-    let task0 = &mut task_manager.inner[0];
+    let task0 = task_manager.task_mut(0);
     task0.task_status = TaskStatus::Running;
     let next_task_cx_ptr = &task0.task_cx as *const TaskContext;
     drop(task_manager); // 释放锁
 
     let mut _unused = TaskContext::zero_init();
+    #[cfg(feature = "lockdep")]
+    crate::sync::lockdep::check_switch();
     unsafe {
         __switch(&mut _unused as *mut TaskContext, next_task_cx_ptr);
     }
     panic!("unreachable in run_first_task!");
 }
 
+/// 切换到下一个就绪的任务，调用前要先设置好当前任务的状态
+/// 没有就绪的任务时，在当前任务的内核栈上等待中断，直到某个任务被唤醒
+fn run_next_task() {
+    let mut idle = false;
+    loop {
+        // 1. 获取锁
+        let mut task_manager = TASK_MANAGER.lock();
+        task_manager.reap_zombies();
+        if let Some(next) = task_manager.find_next_task() {
+            // 2. 获取切换所需的指针
+            let current = task_manager.current_task;
+            task_manager.task_mut(next).task_status = TaskStatus::Running;
+            task_manager.current_task = next;
+            if next == current {
+                // 等待期间当前任务自己被唤醒了，直接继续执行
+                drop(task_manager);
+                if idle {
+                    start_sched_tick();
+                }
+                return;
+            }
+            let current_task_cx_ptr =
+                &mut task_manager.task_mut(current).task_cx as *mut TaskContext;
+            let next_task_cx_ptr = &task_manager.task(next).task_cx as *const TaskContext;
+            // 3. 显式释放锁！
+            drop(task_manager);
+            // 有任务可以运行了，恢复调度时钟
+            if idle {
+                start_sched_tick();
+            }
+
+            // 4. 进行切换
+            #[cfg(feature = "lockdep")]
+            crate::sync::lockdep::check_switch();
+            unsafe {
+                __switch(current_task_cx_ptr, next_task_cx_ptr);
+            }
+            return;
+        }
+
+        if !task_manager.has_blocked_task() {
+            drop(task_manager);
+            info!("All tasks completed!");
+            shutdown();
+        }
+        // 释放锁后再等待，中断处理函数可能需要唤醒任务
+        drop(task_manager);
+        // 没有任务可以运行时停掉调度时钟，只在真正有定时器到期或外部中断时才醒来
+        stop_sched_tick();
+        idle = true;
+        wait_for_interrupt();
+    }
+}
+
+// 调度时钟到期后设置，从中断返回用户态之前检查，需要时切换任务
+static NEED_RESCHED: AtomicBool = AtomicBool::new(false);
+
+// 调度时钟对应的周期定时器，空闲时为 None
+static SCHED_TICK: IrqSafeSpinLock<Option<TimerId>> = IrqSafeSpinLock::new(None);
+
+/// 启动调度时钟，已经启动时什么都不做
+pub fn start_sched_tick() {
+    let mut tick = SCHED_TICK.lock();
+    if tick.is_none() {
+        let period = SCHED_TICK_MS * 1_000_000;
+        *tick = Some(timer::add_timer(
+            timer::monotonic_ns() + period,
+            Some(period),
+            set_need_resched,
+        ));
+    }
+}
+
+/// 停止调度时钟，此后只有真正的定时器会产生时钟中断
+fn stop_sched_tick() {
+    if let Some(id) = SCHED_TICK.lock().take() {
+        timer::cancel_timer(id);
+    }
+}
+
+/// 请求在返回用户态之前重新调度
+fn set_need_resched() {
+    NEED_RESCHED.store(true, Ordering::Relaxed);
+}
+
+/// 读取并清除重新调度的请求
+pub fn take_need_resched() -> bool {
+    NEED_RESCHED.swap(false, Ordering::Relaxed)
+}
+
+/// 当前任务主动让出 CPU
 pub fn suspend_current_and_run_next() {
-    // 1. 获取锁
     let mut task_manager = TASK_MANAGER.lock();
-    // 2. 获取切换所需的指针
-    let task_cx_ptr = task_manager.find_next_task_cx();
-    // 3. 显式释放锁！
+    let current = task_manager.current_task;
+    task_manager.task_mut(current).task_status = TaskStatus::Ready;
+    drop(task_manager);
+    run_next_task();
+}
+
+/// 阻塞当前任务，直到有人调用 wakeup_task 唤醒它
+/// 内核中的中断是关闭的，所以在检查条件和阻塞之间不会有中断处理函数插进来唤醒任务
+pub fn block_current_and_run_next() {
+    let mut task_manager = TASK_MANAGER.lock();
+    let current = task_manager.current_task;
+    task_manager.task_mut(current).task_status = TaskStatus::Blocked;
     drop(task_manager);
+    run_next_task();
+}
+
+/// 当前线程退出；主线程退出时整个进程退出
+pub fn exit_current_and_run_next(exit_code: i32) -> ! {
+    exit_current(exit_code, false)
+}
+
+/// 当前线程所在的整个进程退出，例如进程被信号杀死
+pub fn exit_current_process_and_run_next(exit_code: i32) -> ! {
+    exit_current(exit_code, true)
+}
 
-    // 4. 进行切换
-    if let Some((current, next)) = task_cx_ptr {
-        unsafe {
-            __switch(current, next);
+fn exit_current(exit_code: i32, whole_process: bool) -> ! {
+    let mut task_manager = TASK_MANAGER.lock();
+    let current = task_manager.current_task;
+    let task = task_manager.task_mut(current);
+    task.task_status = TaskStatus::Exited;
+    task.exit_code = Some(exit_code);
+    let (pid, tid) = (task.pid, task.tid);
+    if whole_process || tid == 0 {
+        // 进程中的其他线程不再运行，切换到别的任务之后由 reap_zombies 回收整个进程
+        let process = task_manager.process(pid);
+        let name = process.name;
+        let threads: Vec<usize> = process.threads.iter().flatten().copied().collect();
+        for &id in &threads {
+            task_manager.task_mut(id).task_status = TaskStatus::Exited;
         }
+        task_manager.zombies.push(pid);
+        drop(task_manager);
+        // 阻塞在 futex 上的线程不会再被唤醒，从队列中移除，免得 wake 把它们算作唤醒的任务
+        futex::remove_waiters(&threads);
+        info!("Process {} ({}) exited with code {}", pid, name, exit_code);
     } else {
-        crate::println!("All tasks completed!");
-        loop {}
+        // 单核上被唤醒的 waittid 要等到这个线程切换出去之后才能运行，回收它的内核栈是安全的
+        let thread_exit = task_manager.process(pid).thread_exit.clone();
+        drop(task_manager);
+        debug!(
+            "Thread {} of process {} exited with code {}",
+            tid, pid, exit_code
+        );
+        thread_exit.notify_all();
     }
+    run_next_task();
+    panic!("unreachable in exit_current_and_run_next!");
+}
+
+/// 在当前进程中创建一个线程，从 entry 开始执行，a0 为 arg，返回新线程的 tid
+pub fn thread_create(entry: usize, arg: usize) -> usize {
+    let mut task_manager = TASK_MANAGER.lock();
+    let pid = task_manager.current().pid;
+    task_manager.spawn_thread(pid, entry, arg)
 }
 
-lazy_static! {
-    pub static ref TASK_MANAGER: spin::Mutex<TaskManager> = spin::Mutex::new(TaskManager {
-        inner: Vec::new(),
-        current_task: 0,
+/// 当前线程在进程中的编号
+pub fn current_tid() -> usize {
+    TASK_MANAGER.lock().current().tid
+}
+
+/// 等待当前进程中的线程 tid 退出，回收它的资源并返回它的退出码
+pub fn waittid(tid: usize) -> Result<i32, isize> {
+    let task_manager = TASK_MANAGER.lock();
+    if task_manager.current().tid == tid {
+        return Err(-EDEADLK);
+    }
+    let thread_exit = task_manager.current_process().thread_exit.clone();
+    drop(task_manager);
+
+    let mut result = Err(-ESRCH);
+    thread_exit.wait_until(|| {
+        let mut task_manager = TASK_MANAGER.lock();
+        let Some(id) = task_manager
+            .current_process()
+            .threads
+            .get(tid)
+            .copied()
+            .flatten()
+        else {
+            return true;
+        };
+        let Some(exit_code) = task_manager.task(id).exit_code else {
+            return false;
+        };
+        let task = task_manager.inner[id].take().unwrap();
+        let process = task_manager.current_process_mut();
+        task.release_user_res(process);
+        process.threads[tid] = None;
+        result = Ok(exit_code);
+        true
     });
+    result
 }
+
+/// 唤醒被阻塞的任务 task_id，让它重新参与调度；任务已经被回收时什么都不做
+pub fn wakeup_task(task_id: usize) {
+    let mut task_manager = TASK_MANAGER.lock();
+    if let Some(task) = task_manager.inner.get_mut(task_id).and_then(Option::as_mut) {
+        if task.task_status == TaskStatus::Blocked {
+            task.task_status = TaskStatus::Ready;
+        }
+    }
+}
+
+/// 当前线程的 pid、tid 和进程名，拿不到锁 (例如在持有锁时出错) 时返回 None
+pub fn try_current_task() -> Option<(usize, usize, &'static str)> {
+    let task_manager = TASK_MANAGER.try_lock()?;
+    let task = task_manager
+        .inner
+        .get(task_manager.current_task)?
+        .as_ref()?;
+    Some((
+        task.pid,
+        task.tid,
+        task_manager.processes.get(task.pid)?.as_ref()?.name,
+    ))
+}
+
+/// 拿得到锁时访问当前进程，用在出错时打印现场等不能等锁的地方
+pub fn try_with_current_process<T>(f: impl FnOnce(&ProcessControlBlock) -> T) -> Option<T> {
+    let task_manager = TASK_MANAGER.try_lock()?;
+    let task = task_manager
+        .inner
+        .get(task_manager.current_task)?
+        .as_ref()?;
+    Some(f(task_manager.processes.get(task.pid)?.as_ref()?))
+}
+
+/// 当前线程的全局编号，等待队列和唤醒使用它
+pub fn current_task_id() -> usize {
+    TASK_MANAGER.lock().current_task
+}
+
+pub fn current_user_token() -> usize {
+    TASK_MANAGER.lock().current_process().get_user_token()
+}
+
+pub fn current_trap_cx() -> &'static mut TrapContext {
+    TASK_MANAGER.lock().current().get_trap_cx()
+}
+
+/// 当前线程的 TrapContext 在用户地址空间中的地址
+pub fn current_trap_cx_user_va() -> usize {
+    TASK_MANAGER.lock().current().trap_cx_user_va()
+}
+
+/// 在持有锁的情况下访问当前进程，f 中不能阻塞或切换任务
+pub fn with_current_process<T>(f: impl FnOnce(&mut ProcessControlBlock) -> T) -> T {
+    f(TASK_MANAGER.lock().current_process_mut())
+}
+
+// 时钟中断和外部中断的处理函数会唤醒任务，所以用关中断的自旋锁保护
+pub static TASK_MANAGER: IrqSafeSpinLock<TaskManager> = IrqSafeSpinLock::new(TaskManager {
+    inner: Vec::new(),
+    processes: Vec::new(),
+    zombies: Vec::new(),
+    current_task: 0,
+});
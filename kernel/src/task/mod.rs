@@ -1,89 +1,71 @@
 pub mod context;
 pub mod manager;
+pub mod process;
 pub mod task_block;
 
-use context::TaskContext;
-
-use manager::suspend_current_and_run_next;
+use crate::console;
+use crate::loader;
+use crate::mm::address::PAGE_SIZE;
+use alloc::alloc::{alloc_zeroed, dealloc, Layout};
+use alloc::collections::BTreeSet;
 use manager::TASK_MANAGER;
-use task_block::TaskControlBlock;
-use task_block::TaskStatus;
 
 const KERNEL_STACK_SIZE: usize = 4096 * 2;
-const MAX_APP_NUM: usize = 4;
 
-#[repr(align(4096))]
-#[derive(Debug, Clone, Copy)]
-struct KernelStack {
-    data: [u8; KERNEL_STACK_SIZE],
-}
+// 所有内核栈的栈底，栈回溯时用来判断一个地址在哪个内核栈中
+static KERNEL_STACKS: spin::Mutex<BTreeSet<usize>> = spin::Mutex::new(BTreeSet::new());
 
-#[repr(align(4096))]
-#[derive(Debug, Clone, Copy)]
-struct KernelStackArray {
-    stacks: [KernelStack; MAX_APP_NUM],
+/// 线程的内核栈，从内核堆中分配，线程被回收时释放
+pub struct KernelStack {
+    bottom: usize,
 }
 
-static mut KERNEL_STACK: KernelStackArray = KernelStackArray {
-    stacks: [KernelStack {
-        data: [0; KERNEL_STACK_SIZE],
-    }; MAX_APP_NUM],
-};
+fn kernel_stack_layout() -> Layout {
+    Layout::from_size_align(KERNEL_STACK_SIZE, PAGE_SIZE).unwrap()
+}
 
 impl KernelStack {
-    fn get_sp(&self) -> usize {
-        (self.data.as_ptr() as usize) + KERNEL_STACK_SIZE //栈指针是从高往低正常的，所以指向的是最后
+    pub fn new() -> Self {
+        let bottom = unsafe { alloc_zeroed(kernel_stack_layout()) } as usize;
+        assert!(bottom != 0, "out of memory for kernel stack");
+        KERNEL_STACKS.lock().insert(bottom);
+        Self { bottom }
     }
 
-    pub fn push_context(&self, cx: TaskContext) -> usize {
-        let cx_ptr = (self.get_sp() - core::mem::size_of::<TaskContext>()) as *mut TaskContext;
-        unsafe {
-            *cx_ptr = cx;
-        }
-        cx_ptr as usize
+    /// 栈是从高往低增长的，所以初始的栈指针指向最高处
+    pub fn top(&self) -> usize {
+        self.bottom + KERNEL_STACK_SIZE
     }
 }
 
-pub fn init() {
-    let mut task_manager = TASK_MANAGER.lock();
-
-    let sp_a = unsafe { KERNEL_STACK.stacks[0].get_sp() };
-    let task_a = TaskControlBlock {
-        task_cx: TaskContext::goto_restore(sp_a, task_a_entry as usize),
-        task_status: TaskStatus::Ready,
-    };
-    task_manager.add_task(task_a);
+impl Drop for KernelStack {
+    fn drop(&mut self) {
+        KERNEL_STACKS.lock().remove(&self.bottom);
+        unsafe { dealloc(self.bottom as *mut u8, kernel_stack_layout()) };
+    }
+}
 
-    // 创建任务 B
-    let sp_b = unsafe { KERNEL_STACK.stacks[1].get_sp() };
-    let task_b = TaskControlBlock {
-        task_status: TaskStatus::Ready,
-        task_cx: TaskContext::goto_restore(sp_b, task_b_entry as usize),
+/// 包含地址 addr 的线程内核栈 [bottom, top)
+pub fn kernel_stack_containing(addr: usize) -> Option<(usize, usize)> {
+    // panic 时锁可能正被打断的代码持有
+    let stacks = if console::panicking() {
+        KERNEL_STACKS.try_lock()?
+    } else {
+        KERNEL_STACKS.lock()
     };
-    task_manager.add_task(task_b);
+    let &bottom = stacks.range(..=addr).next_back()?;
+    (addr < bottom + KERNEL_STACK_SIZE).then_some((bottom, bottom + KERNEL_STACK_SIZE))
 }
 
-// 任务 A 的入口
-fn task_a_entry() {
-    for i in 0..5 {
-        println!("Task A: {}", i);
-        suspend_current_and_run_next(); // 主动让出 CPU
-    }
-    println!("Task A finished!");
-    // 任务结束后的处理比较麻烦（需要 exit），这里先死循环
-    loop {
-        suspend_current_and_run_next();
+pub fn init() {
+    let mut task_manager = TASK_MANAGER.lock();
+    for i in 0..loader::get_num_app() {
+        let name = loader::get_app_name(i);
+        info!("Loading app {}: {}", i, name);
+        task_manager.add_process(name, loader::get_app_data(i));
     }
-}
+    drop(task_manager);
 
-// 任务 B 的入口
-fn task_b_entry() {
-    for i in 0..5 {
-        println!("Task B: {}", i);
-        suspend_current_and_run_next();
-    }
-    println!("Task B finished!");
-    loop {
-        suspend_current_and_run_next();
-    }
+    // 调度时钟：每个周期请求一次重新调度，用户任务不主动让出 CPU 也会被切换
+    manager::start_sched_tick();
 }
@@ -0,0 +1,74 @@
+use crate::fs::{File, Stdin, Stdout};
+use crate::mm::memory_set::MemorySet;
+use crate::sync::{Condvar, Mutex, Semaphore, WaitQueue};
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// 进程：拥有地址空间、文件描述符表和同步对象，进程中的所有线程共享这些资源
+pub struct ProcessControlBlock {
+    pub pid: usize,
+    pub name: &'static str,
+    pub memory_set: MemorySet,
+    // 各个线程的用户栈从这里开始依次向上排列
+    pub user_stack_base: usize,
+    // 文件描述符表，下标就是文件描述符，None 表示空闲
+    pub fd_table: Vec<Option<Arc<dyn File>>>,
+    // 用户程序创建的同步对象，下标就是系统调用中使用的编号
+    pub mutex_list: Vec<Option<Arc<Mutex>>>,
+    pub semaphore_list: Vec<Option<Arc<Semaphore>>>,
+    pub condvar_list: Vec<Option<Arc<Condvar>>>,
+    // 线程编号 (tid) -> 线程在 TaskManager 中的编号，None 表示这个 tid 空闲
+    pub threads: Vec<Option<usize>>,
+    // 有线程退出时唤醒等待它的 waittid
+    pub thread_exit: Arc<WaitQueue>,
+}
+
+impl ProcessControlBlock {
+    /// 从 ELF 创建一个还没有线程的进程，返回进程和程序入口
+    pub fn new(pid: usize, name: &'static str, elf_data: &[u8]) -> (Self, usize) {
+        let (memory_set, user_stack_base, entry_point) = MemorySet::from_elf(elf_data);
+        let process = Self {
+            pid,
+            name,
+            memory_set,
+            user_stack_base,
+            // 0/1/2：标准输入、标准输出、标准错误
+            fd_table: vec![
+                Some(Arc::new(Stdin)),
+                Some(Arc::new(Stdout)),
+                Some(Arc::new(Stdout)),
+            ],
+            mutex_list: Vec::new(),
+            semaphore_list: Vec::new(),
+            condvar_list: Vec::new(),
+            threads: Vec::new(),
+            thread_exit: Arc::new(WaitQueue::new()),
+        };
+        (process, entry_point)
+    }
+
+    pub fn get_user_token(&self) -> usize {
+        self.memory_set.token()
+    }
+
+    /// 分配一个最小的空闲文件描述符
+    pub fn alloc_fd(&mut self) -> usize {
+        if let Some(fd) = self.fd_table.iter().position(|file| file.is_none()) {
+            fd
+        } else {
+            self.fd_table.push(None);
+            self.fd_table.len() - 1
+        }
+    }
+
+    /// 分配一个最小的空闲线程编号
+    pub fn alloc_tid(&mut self) -> usize {
+        if let Some(tid) = self.threads.iter().position(|thread| thread.is_none()) {
+            tid
+        } else {
+            self.threads.push(None);
+            self.threads.len() - 1
+        }
+    }
+}
@@ -1,17 +1,106 @@
-use lazy_static::lazy_static;
-
 use super::context::TaskContext;
+use super::process::ProcessControlBlock;
+use super::KernelStack;
+use crate::config::{TRAP_CONTEXT, USER_STACK_SIZE};
+use crate::mm::address::{PhysPageNum, VirtAddr, PAGE_SIZE};
+use crate::mm::memory_set::{kernel_token, MapPermission};
+use crate::trap::context::TrapContext;
+use crate::trap::{trap_handler, trap_return};
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum TaskStatus {
-    UnInit,
     Ready,
     Running,
+    Blocked, // 等待某个事件 (如键盘输入)，被唤醒之前不会被调度
     Exited,
 }
 
-#[derive(Clone, Copy)]
+/// 线程 tid 的用户栈栈底，相邻两个用户栈之间空出一页作为保护页
+fn user_stack_bottom(user_stack_base: usize, tid: usize) -> usize {
+    user_stack_base + tid * (PAGE_SIZE + USER_STACK_SIZE)
+}
+
+/// 线程 tid 的 TrapContext 在用户地址空间中的地址，从跳板页下面依次向下排列
+fn trap_cx_user_va(tid: usize) -> usize {
+    TRAP_CONTEXT - tid * PAGE_SIZE
+}
+
+/// 线程：调度的基本单位，拥有自己的内核栈、用户栈和 TrapContext
 pub struct TaskControlBlock {
+    pub pid: usize, // 所属的进程
+    pub tid: usize, // 在进程中的编号，主线程为 0
     pub task_status: TaskStatus,
     pub task_cx: TaskContext,
+    pub trap_cx_ppn: PhysPageNum, // TrapContext 所在的物理页
+    // 只是为了让内核栈和线程一起释放，运行时通过 sp 使用它
+    _kernel_stack: KernelStack,
+    pub exit_code: Option<i32>,
+}
+
+impl TaskControlBlock {
+    /// 在进程中创建线程 tid：映射它的用户栈和 TrapContext，进入用户态后从 entry 开始执行，a0 为 arg
+    pub fn new(process: &mut ProcessControlBlock, tid: usize, entry: usize, arg: usize) -> Self {
+        let user_stack_bottom = user_stack_bottom(process.user_stack_base, tid);
+        let user_stack_top = user_stack_bottom + USER_STACK_SIZE;
+        process.memory_set.insert_framed_area(
+            VirtAddr(user_stack_bottom),
+            VirtAddr(user_stack_top),
+            MapPermission::READ | MapPermission::WRITE | MapPermission::U,
+        );
+        // TrapContext 用户态不能访问
+        let trap_cx_va = trap_cx_user_va(tid);
+        process.memory_set.insert_framed_area(
+            VirtAddr(trap_cx_va),
+            VirtAddr(trap_cx_va + PAGE_SIZE),
+            MapPermission::READ | MapPermission::WRITE,
+        );
+        let trap_cx_ppn = process
+            .memory_set
+            .translate(VirtAddr(trap_cx_va).floor())
+            .unwrap()
+            .ppn();
+
+        let kernel_stack = KernelStack::new();
+        let kernel_sp = kernel_stack.top();
+        let task = Self {
+            pid: process.pid,
+            tid,
+            task_status: TaskStatus::Ready,
+            // 第一次被调度时从 trap_return 开始执行，进入用户态
+            task_cx: TaskContext::goto_restore(kernel_sp, trap_return as *const () as usize),
+            trap_cx_ppn,
+            _kernel_stack: kernel_stack,
+            exit_code: None,
+        };
+        let trap_cx = task.get_trap_cx();
+        *trap_cx = TrapContext::app_init_context(
+            entry,
+            user_stack_top,
+            kernel_token(),
+            kernel_sp,
+            trap_handler as *const () as usize,
+        );
+        trap_cx.x[10] = arg;
+        task
+    }
+
+    pub fn get_trap_cx(&self) -> &'static mut TrapContext {
+        self.trap_cx_ppn.get_mut()
+    }
+
+    /// TrapContext 在用户地址空间中的地址，返回用户态时交给 __restore
+    pub fn trap_cx_user_va(&self) -> usize {
+        trap_cx_user_va(self.tid)
+    }
+
+    /// 回收线程时，取消它的用户栈和 TrapContext 的映射
+    pub fn release_user_res(&self, process: &mut ProcessControlBlock) {
+        let user_stack_bottom = user_stack_bottom(process.user_stack_base, self.tid);
+        process
+            .memory_set
+            .remove_area_with_start_vpn(VirtAddr(user_stack_bottom).floor());
+        process
+            .memory_set
+            .remove_area_with_start_vpn(VirtAddr(self.trap_cx_user_va()).floor());
+    }
 }
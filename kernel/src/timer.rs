@@ -0,0 +1,204 @@
+use crate::board;
+use crate::cpu;
+use crate::sbi;
+use crate::sync::{IrqSafeSpinLock, WaitQueue};
+use crate::task::manager::wakeup_task;
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use core::arch::asm;
+use core::sync::atomic::{AtomicU64, Ordering};
+
+// 时间：time CSR 记录开机以来的时钟周期数，频率来自设备树的 /cpus/timebase-frequency
+// 单调时钟从开机时的 0 开始；实时时钟 = 开机时刻的 UNIX 时间 + 单调时钟
+
+pub const NSEC_PER_SEC: u64 = 1_000_000_000;
+const USEC_PER_SEC: u64 = 1_000_000;
+
+/// 读取 time CSR：开机以来经过的时钟周期数
+pub fn get_time() -> u64 {
+    let time: u64;
+    unsafe { asm!("rdtime {}", out(reg) time) };
+    time
+}
+
+/// 把时钟周期数换算成 unit 分之一秒，先除后乘避免溢出
+fn ticks_to(ticks: u64, unit: u64) -> u64 {
+    let freq = board::timebase_freq() as u64;
+    ticks / freq * unit + ticks % freq * unit / freq
+}
+
+/// 把纳秒换算成时钟周期数，向上取整，保证不会提前到期
+fn ns_to_ticks(ns: u64) -> u64 {
+    let freq = board::timebase_freq() as u64;
+    ns / NSEC_PER_SEC * freq + (ns % NSEC_PER_SEC * freq).div_ceil(NSEC_PER_SEC)
+}
+
+/// 开机以来经过的纳秒数
+pub fn monotonic_ns() -> u64 {
+    ticks_to(get_time(), NSEC_PER_SEC)
+}
+
+/// 开机以来经过的微秒数
+pub fn monotonic_us() -> u64 {
+    ticks_to(get_time(), USEC_PER_SEC)
+}
+
+// 开机时刻的 UNIX 时间 (纳秒)，由 RTC 驱动初始化；没有 RTC 时为 0，即从 1970-01-01 开始计时
+static BOOT_REALTIME_NS: AtomicU64 = AtomicU64::new(0);
+
+/// 当前的 UNIX 时间 (纳秒)
+pub fn realtime_ns() -> u64 {
+    BOOT_REALTIME_NS.load(Ordering::Relaxed) + monotonic_ns()
+}
+
+/// 设置当前的 UNIX 时间 (纳秒)，只影响实时时钟，单调时钟不变
+pub fn set_realtime_ns(ns: u64) {
+    BOOT_REALTIME_NS.store(ns.saturating_sub(monotonic_ns()), Ordering::Relaxed);
+}
+
+// 定时器：每个 hart 同一时刻只能通过 SBI 设置一个到期时间，
+// 这里把所有定时器按到期时间排成队列，总是把最早的那个设置给 SBI，到期后依次执行回调
+// 睡眠的任务、调度时钟和 I/O 超时都通过定时器实现
+
+/// 定时器的编号，用于取消
+pub type TimerId = u64;
+
+/// 定时器到期时执行的回调，在时钟中断中调用，不能阻塞
+pub type TimerCallback = Box<dyn FnMut() + Send>;
+
+struct Timer {
+    period: Option<u64>, // 周期定时器的周期 (纳秒)，None 表示只触发一次
+    callback: TimerCallback,
+}
+
+struct TimerQueue {
+    // 按 (到期时间, 编号) 排序，第一个就是最早到期的
+    timers: BTreeMap<(u64, TimerId), Timer>,
+    // 编号 -> 到期时间，取消时用来找到定时器
+    deadlines: BTreeMap<TimerId, u64>,
+    next_id: TimerId,
+    // 正在执行回调的定时器 (已经从队列中取出)，它是否是周期定时器，以及它在回调期间是否被取消
+    running: Option<TimerId>,
+    running_periodic: bool,
+    running_cancelled: bool,
+}
+
+static TIMERS: IrqSafeSpinLock<TimerQueue> = IrqSafeSpinLock::new(TimerQueue {
+    timers: BTreeMap::new(),
+    deadlines: BTreeMap::new(),
+    next_id: 0,
+    running: None,
+    running_periodic: false,
+    running_cancelled: false,
+});
+
+impl TimerQueue {
+    fn insert(&mut self, id: TimerId, deadline: u64, timer: Timer) {
+        self.timers.insert((deadline, id), timer);
+        self.deadlines.insert(id, deadline);
+    }
+
+    /// 取出一个已经到期的定时器
+    fn pop_expired(&mut self, now: u64) -> Option<(TimerId, u64, Timer)> {
+        let entry = self.timers.first_entry().filter(|e| e.key().0 <= now)?;
+        let (deadline, id) = *entry.key();
+        let timer = entry.remove();
+        self.deadlines.remove(&id);
+        Some((id, deadline, timer))
+    }
+
+    /// 把最早的到期时间设置给 SBI，没有定时器时不产生时钟中断
+    fn program(&self) {
+        match self.timers.first_key_value() {
+            Some((&(deadline, _), _)) => sbi::set_timer(ns_to_ticks(deadline) as usize),
+            None => sbi::set_timer(usize::MAX),
+        }
+    }
+}
+
+/// 添加一个在单调时钟到达 deadline (纳秒) 时触发的定时器
+/// period 不为 None 时，之后每隔 period 纳秒再触发一次，直到被取消
+pub fn add_timer(
+    deadline: u64,
+    period: Option<u64>,
+    callback: impl FnMut() + Send + 'static,
+) -> TimerId {
+    assert!(period != Some(0), "timer period must not be zero");
+    let mut queue = TIMERS.lock();
+    let id = queue.next_id;
+    queue.next_id += 1;
+    let timer = Timer {
+        period,
+        callback: Box::new(callback),
+    };
+    queue.insert(id, deadline, timer);
+    queue.program();
+    id
+}
+
+/// 取消定时器，返回它是否还没有触发过 (周期定时器总是返回 true)
+/// 在定时器自己的回调中取消周期定时器也是可以的；一次性定时器在自己的回调中已经触发，返回 false
+pub fn cancel_timer(id: TimerId) -> bool {
+    let mut queue = TIMERS.lock();
+    if queue.running == Some(id) {
+        queue.running_cancelled = true;
+        return queue.running_periodic;
+    }
+    let Some(deadline) = queue.deadlines.remove(&id) else {
+        return false;
+    };
+    queue.timers.remove(&(deadline, id));
+    queue.program();
+    true
+}
+
+/// 在 deadline 时唤醒任务 task_id
+pub fn wakeup_at(deadline: u64, task_id: usize) -> TimerId {
+    add_timer(deadline, None, move || wakeup_task(task_id))
+}
+
+/// 阻塞当前任务，直到单调时钟到达 deadline (纳秒)
+pub fn sleep_until(deadline: u64) {
+    // 没有人会 notify 这个队列，只会超时；被其他原因唤醒时，时间没到就继续睡
+    let queue = WaitQueue::new();
+    while monotonic_ns() < deadline {
+        queue.wait_timeout(deadline);
+    }
+}
+
+/// 时钟中断处理：依次执行所有到期的定时器
+/// 回调执行时不持有队列的锁，回调中可以添加或取消定时器
+pub fn handle_interrupt() {
+    let now = monotonic_ns();
+    loop {
+        let mut queue = TIMERS.lock();
+        let Some((id, deadline, mut timer)) = queue.pop_expired(now) else {
+            queue.program();
+            return;
+        };
+        queue.running = Some(id);
+        queue.running_periodic = timer.period.is_some();
+        queue.running_cancelled = false;
+        drop(queue);
+
+        (timer.callback)();
+
+        let mut queue = TIMERS.lock();
+        queue.running = None;
+        if let Some(period) = timer.period.filter(|_| !queue.running_cancelled) {
+            // 错过了好几个周期时 (例如长时间关中断) 不补发，直接排到下一个周期
+            let mut next = deadline + period;
+            if next <= now {
+                next = now + period;
+            }
+            queue.insert(id, next, timer);
+        }
+    }
+}
+
+pub fn init() {
+    // 在添加定时器之前不需要时钟中断
+    sbi::set_timer(usize::MAX);
+    cpu::enable_timer_interrupt();
+    info!("timebase frequency {} Hz", board::timebase_freq());
+}
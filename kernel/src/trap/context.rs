@@ -0,0 +1,51 @@
+use crate::cpu::hart_id;
+use core::arch::asm;
+
+// sstatus.SPP: 陷入前的特权级，0 表示 U 模式
+const SSTATUS_SPP: usize = 1 << 8;
+
+/// 陷入时保存的现场
+/// 布局必须和 trap.S 中的保存顺序一致
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+pub struct TrapContext {
+    pub x: [usize; 32], // 通用寄存器 x0-x31
+    pub sstatus: usize,
+    pub sepc: usize, // 陷入时的 pc，sret 会返回到这里
+    // 以下字段只在从用户态陷入时使用，由 __alltraps 读取
+    pub kernel_satp: usize,  // 内核地址空间的 token
+    pub kernel_sp: usize,    // 当前任务内核栈的栈顶
+    pub trap_handler: usize, // trap_handler 的地址
+    pub kernel_tp: usize,    // 内核中 tp 保存着 hart id，用户程序可能会修改 tp
+}
+
+impl TrapContext {
+    pub fn set_sp(&mut self, sp: usize) {
+        self.x[2] = sp;
+    }
+
+    /// 用户程序第一次进入用户态时的现场：从 entry 开始执行，栈指针为 sp
+    pub fn app_init_context(
+        entry: usize,
+        sp: usize,
+        kernel_satp: usize,
+        kernel_sp: usize,
+        trap_handler: usize,
+    ) -> Self {
+        let mut sstatus: usize;
+        unsafe { asm!("csrr {}, sstatus", out(reg) sstatus) };
+        // sret 之后回到 U 模式
+        sstatus &= !SSTATUS_SPP;
+        let mut cx = Self {
+            x: [0; 32],
+            sstatus,
+            sepc: entry,
+            kernel_satp,
+            kernel_sp,
+            trap_handler,
+            kernel_tp: hart_id(),
+        };
+        cx.set_sp(sp);
+        cx
+    }
+}
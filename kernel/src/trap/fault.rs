@@ -0,0 +1,156 @@
+use super::context::TrapContext;
+use crate::ksym;
+use crate::mm::address::VirtAddr;
+use crate::mm::page_table::{flags_str, levels, PageTable, PageTableEntry};
+use crate::task::manager::{try_current_task, try_with_current_process};
+use core::arch::asm;
+
+// 内核态异常的现场报告：异常原因、寄存器、出错地址的页表查找过程和当前任务
+
+// 通用寄存器的 ABI 名字，下标就是寄存器编号
+const REG_NAMES: [&str; 32] = [
+    "zero", "ra", "sp", "gp", "tp", "t0", "t1", "t2", "s0", "s1", "a0", "a1", "a2", "a3", "a4",
+    "a5", "a6", "a7", "s2", "s3", "s4", "s5", "s6", "s7", "s8", "s9", "s10", "s11", "t3", "t4",
+    "t5", "t6",
+];
+
+/// 异常编号对应的名字 (RISC-V 特权级手册中的 scause 编码)
+pub fn exception_name(code: usize) -> &'static str {
+    match code {
+        0 => "Instruction address misaligned",
+        1 => "Instruction access fault",
+        2 => "Illegal instruction",
+        3 => "Breakpoint",
+        4 => "Load address misaligned",
+        5 => "Load access fault",
+        6 => "Store/AMO address misaligned",
+        7 => "Store/AMO access fault",
+        8 => "Environment call from U-mode",
+        9 => "Environment call from S-mode",
+        12 => "Instruction page fault",
+        13 => "Load page fault",
+        15 => "Store/AMO page fault",
+        18 => "Software check",
+        19 => "Hardware error",
+        _ => "Unknown exception",
+    }
+}
+
+/// 中断编号对应的名字
+pub fn interrupt_name(code: usize) -> &'static str {
+    match code {
+        1 => "Supervisor software interrupt",
+        5 => "Supervisor timer interrupt",
+        9 => "Supervisor external interrupt",
+        13 => "Counter-overflow interrupt",
+        _ => "Unknown interrupt",
+    }
+}
+
+// 用户程序出错时结束它所用的信号，取值与 Linux 相同
+const SIGILL: i32 = 4;
+const SIGTRAP: i32 = 5;
+const SIGBUS: i32 = 7;
+const SIGSEGV: i32 = 11;
+
+/// 用户态异常对应的信号和信号名
+pub fn user_fault_signal(code: usize) -> (i32, &'static str) {
+    match code {
+        2 => (SIGILL, "SIGILL"),
+        3 => (SIGTRAP, "SIGTRAP"),
+        0 | 4 | 6 => (SIGBUS, "SIGBUS"),
+        // 访问错误、缺页，以及其他不认识的异常
+        _ => (SIGSEGV, "SIGSEGV"),
+    }
+}
+
+/// stval 中是出错的虚拟地址的异常 (地址不对齐、访问错误、缺页)
+fn has_fault_address(code: usize) -> bool {
+    matches!(code, 0 | 1 | 4..=7 | 12 | 13 | 15)
+}
+
+fn print_pte(level: usize, pte: &PageTableEntry) {
+    let flags = flags_str(pte.flags());
+    println!(
+        "  L{} pte {:#018x}: ppn {:#x} flags {}",
+        levels() - 1 - level,
+        pte.bits,
+        pte.ppn().0,
+        core::str::from_utf8(&flags).unwrap()
+    );
+}
+
+/// 当前 satp 指向的页表的视图，出错时即使正持有地址空间的锁也能查询
+/// mm::init 开启分页之前 satp 为 0 (Bare 模式)，这时返回 None
+fn current_page_table() -> Option<PageTable> {
+    let satp: usize;
+    unsafe { asm!("csrr {}, satp", out(reg) satp) };
+    PageTable::from_token(satp)
+}
+
+/// 出错地址在当前页表 (satp) 中的查找过程
+fn print_page_walk(page_table: &PageTable, va: usize) {
+    println!("page walk for {:#x}:", va);
+    for (level, pte) in page_table.walk(VirtAddr(va).floor()).iter().enumerate() {
+        let Some(pte) = pte else {
+            break;
+        };
+        print_pte(level, pte);
+        if !pte.is_valid() {
+            println!("  (not mapped)");
+        }
+    }
+}
+
+fn print_registers(cx: &TrapContext) {
+    // 每行 4 个寄存器
+    for i in (0..32).step_by(4) {
+        println!(
+            "{:>4}: {:#018x}  {:>4}: {:#018x}  {:>4}: {:#018x}  {:>4}: {:#018x}",
+            REG_NAMES[i],
+            cx.x[i],
+            REG_NAMES[i + 1],
+            cx.x[i + 1],
+            REG_NAMES[i + 2],
+            cx.x[i + 2],
+            REG_NAMES[i + 3],
+            cx.x[i + 3]
+        );
+    }
+    println!("sstatus: {:#018x}", cx.sstatus);
+}
+
+/// 打印内核态异常的现场，调用者随后 panic
+pub fn report_kernel_fault(cx: &TrapContext, code: usize, stval: usize) {
+    println!(
+        "Kernel exception: {} (scause = {})",
+        exception_name(code),
+        code
+    );
+    match ksym::lookup(cx.sepc) {
+        Some((name, offset)) => println!("sepc: {:#x} {}+{:#x}", cx.sepc, name, offset),
+        None => println!("sepc: {:#x}", cx.sepc),
+    }
+    println!("stval: {:#x}", stval);
+    match try_current_task() {
+        Some((pid, tid, name)) => println!("current task: pid {} ({}) tid {}", pid, name, tid),
+        None => println!("current task: unknown"),
+    }
+    print_registers(cx);
+    if has_fault_address(code) {
+        match current_page_table() {
+            Some(page_table) => print_page_walk(&page_table, stval),
+            None => println!("page walk for {:#x}: paging disabled", stval),
+        }
+    }
+}
+
+/// panic 时打印当前的地址空间：当前进程的各个区域 (拿得到锁时) 和 satp 指向的页表
+/// 内核异常随后也会 panic，出错的地址可能来自当前进程 (例如系统调用访问用户内存)，这些信息可以作为参考
+pub fn dump_address_space() {
+    try_with_current_process(|process| process.memory_set.dump());
+    match current_page_table() {
+        Some(page_table) => page_table.dump(),
+        None => println!("page table: paging disabled"),
+    }
+}
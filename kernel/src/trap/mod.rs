@@ -0,0 +1,153 @@
+pub mod context;
+mod fault;
+
+pub use fault::dump_address_space;
+
+use crate::config::TRAMPOLINE;
+use crate::drivers::plic;
+use crate::syscall::syscall;
+use crate::task::manager::{
+    current_trap_cx, current_trap_cx_user_va, current_user_token,
+    exit_current_process_and_run_next, suspend_current_and_run_next, take_need_resched,
+    try_current_task,
+};
+use crate::timer;
+use context::TrapContext;
+use core::arch::{asm, global_asm};
+
+global_asm!(include_str!("trap.S"));
+
+extern "C" {
+    fn __kernel_trap();
+    fn __alltraps();
+    fn __restore();
+}
+
+// scause 最高位为 1 表示中断，其余位是中断 / 异常的编号
+const SCAUSE_INTERRUPT: usize = 1 << (usize::BITS - 1);
+// S 模式时钟中断 (由 SBI 设置)
+const IRQ_S_TIMER: usize = 5;
+// S 模式外部中断 (由 PLIC 转发)
+const IRQ_S_EXTERNAL: usize = 9;
+// 来自 U 模式的 ecall
+const EXCEPTION_USER_ENV_CALL: usize = 8;
+
+pub fn init() {
+    set_kernel_trap_entry();
+}
+
+/// 在内核中时，陷入由 __kernel_trap 处理
+fn set_kernel_trap_entry() {
+    unsafe { asm!("csrw stvec, {}", in(reg) __kernel_trap as *const () as usize) };
+}
+
+/// 返回用户态之前，把陷入入口设置为跳板页上的 __alltraps
+fn set_user_trap_entry() {
+    unsafe { asm!("csrw stvec, {}", in(reg) TRAMPOLINE) };
+}
+
+fn read_cause() -> (usize, usize) {
+    let scause: usize;
+    let stval: usize;
+    unsafe {
+        asm!("csrr {}, scause", out(reg) scause);
+        asm!("csrr {}, stval", out(reg) stval);
+    }
+    (scause, stval)
+}
+
+/// 用户态陷入的处理函数，__alltraps 切换到内核地址空间后跳转到这里
+#[no_mangle]
+pub extern "C" fn trap_handler() -> ! {
+    set_kernel_trap_entry();
+    let (scause, stval) = read_cause();
+
+    if scause & SCAUSE_INTERRUPT != 0 {
+        match scause & !SCAUSE_INTERRUPT {
+            IRQ_S_TIMER => timer::handle_interrupt(),
+            IRQ_S_EXTERNAL => plic::handle_irq(),
+            code => panic!(
+                "Unsupported interrupt from user: {} ({})",
+                fault::interrupt_name(code),
+                code
+            ),
+        }
+        // 调度时钟到期，把 CPU 让给其他任务
+        if take_need_resched() {
+            suspend_current_and_run_next();
+        }
+    } else {
+        match scause {
+            EXCEPTION_USER_ENV_CALL => {
+                let cx = current_trap_cx();
+                // 返回时跳过 ecall 指令
+                cx.sepc += 4;
+                let ret = syscall(
+                    cx.x[17],
+                    [cx.x[10], cx.x[11], cx.x[12], cx.x[13], cx.x[14], cx.x[15]],
+                );
+                // 系统调用期间可能切换过任务，重新获取当前任务的 TrapContext
+                current_trap_cx().x[10] = ret as usize;
+            }
+            // 用户程序的错误只结束这个进程，退出码和 shell 中被信号杀死的进程一样是 128 + 信号
+            code => {
+                let (signal, signal_name) = fault::user_fault_signal(code);
+                let (pid, tid, name) = try_current_task().unwrap();
+                warn!(
+                    "Process {} ({}) thread {} killed by {}: {}, stval = {:#x}, sepc = {:#x}",
+                    pid,
+                    name,
+                    tid,
+                    signal_name,
+                    fault::exception_name(code),
+                    stval,
+                    current_trap_cx().sepc
+                );
+                exit_current_process_and_run_next(128 + signal);
+            }
+        }
+    }
+    trap_return();
+}
+
+/// 回到当前任务的用户态：通过跳板页上的 __restore 切换地址空间并 sret
+#[no_mangle]
+pub extern "C" fn trap_return() -> ! {
+    set_user_trap_entry();
+    let trap_cx_ptr = current_trap_cx_user_va();
+    let user_satp = current_user_token();
+    // __restore 在跳板页中的虚拟地址
+    let restore_va =
+        __restore as *const () as usize - __alltraps as *const () as usize + TRAMPOLINE;
+    unsafe {
+        asm!(
+            "fence.i",
+            "jr {restore_va}",
+            restore_va = in(reg) restore_va,
+            in("a0") trap_cx_ptr,
+            in("a1") user_satp,
+            options(noreturn)
+        );
+    }
+}
+
+/// 内核态陷入的处理函数，由 __kernel_trap 调用
+#[no_mangle]
+pub extern "C" fn kernel_trap_handler(cx: &mut TrapContext) {
+    let (scause, stval) = read_cause();
+
+    if scause & SCAUSE_INTERRUPT != 0 {
+        match scause & !SCAUSE_INTERRUPT {
+            IRQ_S_TIMER => timer::handle_interrupt(),
+            IRQ_S_EXTERNAL => plic::handle_irq(),
+            code => panic!(
+                "Unsupported interrupt in kernel: {} ({})",
+                fault::interrupt_name(code),
+                code
+            ),
+        }
+    } else {
+        fault::report_kernel_fault(cx, scause, stval);
+        panic!("{} in kernel", fault::exception_name(scause));
+    }
+}
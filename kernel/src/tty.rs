@@ -0,0 +1,235 @@
+use crate::console;
+use crate::sync::{IrqSafeSpinLock, WaitQueue};
+use alloc::vec::Vec;
+
+// 终端输入：UART 接收中断 -> 行规程 -> 读取终端的任务
+
+// c_lflag 中内核支持的标志，取值与 Linux 相同
+pub const ISIG: u32 = 0o1; // Ctrl-C 中断读取
+pub const ICANON: u32 = 0o2; // 规范模式：按行读取，支持行编辑
+pub const ECHO: u32 = 0o10; // 回显输入的字符
+
+// 控制字符
+const CTRL_C: u8 = 0x03; // 中断正在进行的读取
+const CTRL_D: u8 = 0x04; // 文件结束 (EOF)
+const BACKSPACE: u8 = 0x08;
+const CTRL_U: u8 = 0x15; // 删除整行
+const DELETE: u8 = 0x7f; // 大多数终端的退格键发送的是 DEL
+
+const RX_BUFFER_SIZE: usize = 256;
+
+/// 定长的环形缓冲区，满了之后丢弃新来的字节
+struct RingBuffer {
+    buf: [u8; RX_BUFFER_SIZE],
+    head: usize, // 最早写入的字节的位置
+    len: usize,
+}
+
+impl RingBuffer {
+    const fn new() -> Self {
+        Self {
+            buf: [0; RX_BUFFER_SIZE],
+            head: 0,
+            len: 0,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn push(&mut self, byte: u8) -> bool {
+        if self.len == RX_BUFFER_SIZE {
+            return false;
+        }
+        self.buf[(self.head + self.len) % RX_BUFFER_SIZE] = byte;
+        self.len += 1;
+        true
+    }
+
+    fn pop(&mut self) -> Option<u8> {
+        if self.is_empty() {
+            return None;
+        }
+        let byte = self.buf[self.head];
+        self.head = (self.head + 1) % RX_BUFFER_SIZE;
+        self.len -= 1;
+        Some(byte)
+    }
+
+    fn pop_back(&mut self) -> Option<u8> {
+        if self.is_empty() {
+            return None;
+        }
+        self.len -= 1;
+        Some(self.buf[(self.head + self.len) % RX_BUFFER_SIZE])
+    }
+
+    fn clear(&mut self) {
+        self.len = 0;
+    }
+}
+
+struct Tty {
+    lflag: u32,
+    // 可以被读走的数据，规范模式下是已经按了回车的完整行
+    ready: RingBuffer,
+    // 规范模式下正在编辑、还没有提交的一行
+    line: RingBuffer,
+    // 在空行上按了 Ctrl-D，下一次读取返回 0
+    eof: bool,
+    // 按了 Ctrl-C，正在等待的读取返回 EINTR
+    interrupted: bool,
+}
+
+static TTY: IrqSafeSpinLock<Tty> = IrqSafeSpinLock::new(Tty {
+    lflag: ISIG | ICANON | ECHO,
+    ready: RingBuffer::new(),
+    line: RingBuffer::new(),
+    eof: false,
+    interrupted: false,
+});
+
+// 等待输入的任务，加锁顺序为 TTY -> READERS
+static READERS: WaitQueue = WaitQueue::new();
+
+impl Tty {
+    fn echo(&self, bytes: &[u8]) {
+        if self.lflag & ECHO != 0 {
+            console::write_bytes(bytes);
+        }
+    }
+
+    /// 退格：删除编辑中的行的最后一个字符 (UTF-8 字符可能占多个字节)
+    fn erase_char(&mut self) -> bool {
+        while let Some(byte) = self.line.pop_back() {
+            // 0b10xxxxxx 是多字节字符的后续字节，要一直删到首字节为止
+            if byte & 0xc0 != 0x80 {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// 把编辑中的行提交给读者
+    fn commit_line(&mut self) {
+        while let Some(byte) = self.line.pop() {
+            self.ready.push(byte);
+        }
+        READERS.notify_all();
+    }
+
+    /// 规范模式下的行规程
+    fn receive_canonical(&mut self, byte: u8) {
+        match byte {
+            CTRL_C if self.lflag & ISIG != 0 => {
+                self.line.clear();
+                self.ready.clear();
+                self.echo(b"^C\n");
+                if READERS.notify_all() > 0 {
+                    self.interrupted = true;
+                }
+            }
+            CTRL_D => {
+                // 空行上的 Ctrl-D 表示文件结束，否则只是把已输入的内容提交 (不带换行)
+                if self.line.is_empty() {
+                    self.eof = true;
+                }
+                self.commit_line();
+            }
+            BACKSPACE | DELETE => {
+                if self.erase_char() {
+                    self.echo(b"\x08 \x08");
+                }
+            }
+            CTRL_U => {
+                while self.erase_char() {
+                    self.echo(b"\x08 \x08");
+                }
+            }
+            b'\r' | b'\n' => {
+                // 行缓冲区满时也要给换行符留出位置
+                if !self.line.push(b'\n') {
+                    self.line.pop_back();
+                    self.line.push(b'\n');
+                }
+                self.echo(b"\n");
+                self.commit_line();
+            }
+            _ => {
+                if self.line.push(byte) {
+                    self.echo(&[byte]);
+                }
+            }
+        }
+    }
+
+    /// 原始模式：每个字节立刻交给读者
+    fn receive_raw(&mut self, byte: u8) {
+        if self.ready.push(byte) {
+            self.echo(&[byte]);
+        }
+        READERS.notify_all();
+    }
+
+    /// 取出最多 max 个字节，规范模式下一次最多读到行尾
+    fn take(&mut self, max: usize) -> Vec<u8> {
+        let mut data = Vec::new();
+        while data.len() < max {
+            let Some(byte) = self.ready.pop() else {
+                break;
+            };
+            data.push(byte);
+            if byte == b'\n' && self.lflag & ICANON != 0 {
+                break;
+            }
+        }
+        data
+    }
+}
+
+/// UART 接收中断收到一个字节
+pub fn receive(byte: u8) {
+    let mut tty = TTY.lock();
+    if tty.lflag & ICANON != 0 {
+        tty.receive_canonical(byte);
+    } else {
+        tty.receive_raw(byte);
+    }
+}
+
+/// 从终端读取最多 max 个字节，没有输入时阻塞当前任务
+/// 返回空的 Vec 表示文件结束，返回 None 表示读取被 Ctrl-C 中断
+pub fn read(max: usize) -> Option<Vec<u8>> {
+    loop {
+        let mut tty = TTY.lock();
+        if tty.interrupted {
+            tty.interrupted = false;
+            return None;
+        }
+        if !tty.ready.is_empty() {
+            return Some(tty.take(max));
+        }
+        if tty.eof {
+            tty.eof = false;
+            return Some(Vec::new());
+        }
+        drop(tty);
+        READERS.wait();
+    }
+}
+
+pub fn lflag() -> u32 {
+    TTY.lock().lflag
+}
+
+/// 修改终端模式，只保留内核支持的标志
+pub fn set_lflag(lflag: u32) {
+    let mut tty = TTY.lock();
+    let was_canonical = tty.lflag & ICANON != 0;
+    tty.lflag = lflag & (ISIG | ICANON | ECHO);
+    // 切换到原始模式时，编辑到一半的行直接交给读者
+    if was_canonical && tty.lflag & ICANON == 0 {
+        tty.commit_line();
+    }
+}
@@ -0,0 +1,114 @@
+use std::env;
+use std::fs;
+use std::process;
+use xmas_elf::sections::SectionData;
+use xmas_elf::symbol_table::{Entry, Type};
+use xmas_elf::ElfFile;
+
+// 把内核 ELF 中的函数符号写入链接脚本预留的 .ksymtab 段，内核的栈回溯据此把地址解析成函数名
+// 用法：ksym <内核 ELF 路径>，原地修改文件
+//
+// .ksymtab 的格式 (小端)：
+//   magic "KSYM" (4) | 符号数量 (4)
+//   符号数组，按地址升序：地址 (8) | 大小 (8) | 名字在字符串区的偏移 (4) | 名字长度 (4)
+//   字符串区：所有名字依次拼接，不以 0 结尾
+// 段的布局必须与 kernel/src/ksym.rs 保持一致
+
+const MAGIC: &[u8; 4] = b"KSYM";
+const HEADER_SIZE: usize = 8;
+const ENTRY_SIZE: usize = 24;
+
+struct Symbol {
+    addr: u64,
+    size: u64,
+    name: String,
+}
+
+fn fail(msg: &str) -> ! {
+    eprintln!("ksym: {}", msg);
+    process::exit(1);
+}
+
+/// 收集 .symtab 中所有函数符号，名字去掉 Rust 的哈希后缀
+fn collect_symbols(elf: &ElfFile) -> Vec<Symbol> {
+    let symtab = elf
+        .find_section_by_name(".symtab")
+        .unwrap_or_else(|| fail("no .symtab, is the kernel stripped?"));
+    let entries = match symtab.get_data(elf) {
+        Ok(SectionData::SymbolTable64(entries)) => entries,
+        _ => fail("unsupported .symtab format"),
+    };
+    let mut symbols: Vec<Symbol> = entries
+        .iter()
+        .filter(|sym| sym.get_type() == Ok(Type::Func) && sym.value() != 0)
+        .filter_map(|sym| {
+            let name = sym.get_name(elf).ok()?;
+            Some(Symbol {
+                addr: sym.value(),
+                size: sym.size(),
+                name: format!("{:#}", rustc_demangle::demangle(name)),
+            })
+        })
+        .collect();
+    symbols.sort_by_key(|sym| sym.addr);
+    symbols.dedup_by_key(|sym| sym.addr);
+    symbols
+}
+
+fn encode(symbols: &[Symbol]) -> Vec<u8> {
+    let mut table = Vec::new();
+    table.extend_from_slice(MAGIC);
+    table.extend_from_slice(&(symbols.len() as u32).to_le_bytes());
+    let mut name_off = 0u32;
+    for sym in symbols {
+        table.extend_from_slice(&sym.addr.to_le_bytes());
+        table.extend_from_slice(&sym.size.to_le_bytes());
+        table.extend_from_slice(&name_off.to_le_bytes());
+        table.extend_from_slice(&(sym.name.len() as u32).to_le_bytes());
+        name_off += sym.name.len() as u32;
+    }
+    assert_eq!(table.len(), HEADER_SIZE + symbols.len() * ENTRY_SIZE);
+    for sym in symbols {
+        table.extend_from_slice(sym.name.as_bytes());
+    }
+    table
+}
+
+fn main() {
+    let path = env::args()
+        .nth(1)
+        .unwrap_or_else(|| fail("usage: ksym <kernel-elf>"));
+    let mut image = fs::read(&path).unwrap_or_else(|err| fail(&format!("{}: {}", path, err)));
+
+    let (offset, size, table, count) = {
+        let elf = ElfFile::new(&image).unwrap_or_else(|err| fail(err));
+        let section = elf
+            .find_section_by_name(".ksymtab")
+            .unwrap_or_else(|| fail("no .ksymtab section in the kernel"));
+        let symbols = collect_symbols(&elf);
+        (
+            section.offset() as usize,
+            section.size() as usize,
+            encode(&symbols),
+            symbols.len(),
+        )
+    };
+    if table.len() > size {
+        fail(&format!(
+            "symbol table needs {} bytes but .ksymtab is only {} bytes",
+            table.len(),
+            size
+        ));
+    }
+
+    let section = &mut image[offset..offset + size];
+    section.fill(0);
+    section[..table.len()].copy_from_slice(&table);
+    fs::write(&path, &image).unwrap_or_else(|err| fail(&format!("{}: {}", path, err)));
+    println!(
+        "ksym: {} symbols, {} / {} bytes of .ksymtab",
+        count,
+        table.len(),
+        size
+    );
+}
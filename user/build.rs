@@ -0,0 +1,10 @@
+use std::env;
+use std::path::PathBuf;
+
+fn main() {
+    // 所有用户程序都使用 src/linker.ld 链接到相同的起始地址
+    let manifest_dir = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
+    let linker_script = manifest_dir.join("src").join("linker.ld");
+    println!("cargo:rustc-link-arg=-T{}", linker_script.display());
+    println!("cargo:rerun-if-changed={}", linker_script.display());
+}
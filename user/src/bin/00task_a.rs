@@ -0,0 +1,17 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::yield_;
+
+#[no_mangle]
+fn main() -> i32 {
+    for i in 0..5 {
+        println!("Task A: {}", i);
+        yield_(); // 主动让出 CPU
+    }
+    println!("Task A finished!");
+    0
+}
@@ -0,0 +1,40 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{read, tcgetattr, tcsetattr, Termios, ECHO, ICANON, STDIN};
+
+#[no_mangle]
+fn main() -> i32 {
+    // 规范模式：内核按行交给我们，行内可以用退格、Ctrl-U 编辑
+    println!("echo: type some lines, Ctrl-D to finish");
+    let mut buf = [0u8; 128];
+    loop {
+        let n = read(STDIN, &mut buf);
+        if n == 0 {
+            break; // Ctrl-D: 文件结束
+        }
+        if n < 0 {
+            println!("echo: read interrupted ({})", n);
+            return 1;
+        }
+        let line = core::str::from_utf8(&buf[..n as usize]).unwrap_or("<invalid utf-8>\n");
+        print!("echo: {}", line);
+    }
+
+    // 原始模式：不回显，每个按键立刻交给我们
+    let mut termios = Termios::default();
+    tcgetattr(STDIN, &mut termios);
+    let saved = termios;
+    termios.c_lflag &= !(ICANON | ECHO);
+    tcsetattr(STDIN, &termios);
+    println!("echo: raw mode, press any key ('q' to quit)");
+    let mut key = [0u8; 1];
+    while read(STDIN, &mut key) == 1 && key[0] != b'q' {
+        println!("echo: key {:#04x}", key[0]);
+    }
+    tcsetattr(STDIN, &saved);
+    0
+}
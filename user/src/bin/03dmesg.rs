@@ -0,0 +1,35 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{close, open, read, write, O_RDONLY, STDOUT};
+
+// EPIPE：要读的消息已经被覆盖，下一次读取从现存最早的消息开始
+const EPIPE: isize = -32;
+
+#[no_mangle]
+fn main() -> i32 {
+    // 每次 read 读到一条消息："级别,序号,时间戳,-;消息\n"
+    let fd = open("/dev/kmsg\0", O_RDONLY);
+    if fd < 0 {
+        println!("dmesg: cannot open /dev/kmsg ({})", fd);
+        return 1;
+    }
+    let fd = fd as usize;
+    let mut buf = [0u8; 1024];
+    loop {
+        let n = read(fd, &mut buf);
+        if n == EPIPE {
+            println!("dmesg: some messages were overwritten");
+            continue;
+        }
+        if n <= 0 {
+            break;
+        }
+        write(STDOUT, &buf[..n as usize]);
+    }
+    close(fd);
+    0
+}
@@ -0,0 +1,14 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+#[no_mangle]
+fn main() -> i32 {
+    // 写空指针会触发缺页异常，内核应该只结束这个任务 (退出码 128 + SIGSEGV = 139)
+    println!("segfault: writing to a null pointer...");
+    unsafe { core::ptr::null_mut::<u8>().write_volatile(0) };
+    println!("segfault: should not reach here!");
+    0
+}
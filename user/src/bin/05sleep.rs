@@ -0,0 +1,18 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{get_time_ms, sleep_ms};
+
+#[no_mangle]
+fn main() -> i32 {
+    // 睡眠期间任务被阻塞，其他任务照常运行
+    let start = get_time_ms();
+    for i in 1..=3 {
+        sleep_ms(500);
+        println!("sleep: woke up #{} after {} ms", i, get_time_ms() - start);
+    }
+    0
+}
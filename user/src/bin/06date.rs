@@ -0,0 +1,43 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{clock_gettime, TimeSpec, CLOCK_REALTIME};
+
+/// 把 1970-01-01 以来的天数换算成公历的年、月、日
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    // 以 0000-03-01 为起点，每 400 年 (146097 天) 一个周期，闰日落在每年的最后一天
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[no_mangle]
+fn main() -> i32 {
+    let mut tp = TimeSpec::default();
+    if clock_gettime(CLOCK_REALTIME, &mut tp) < 0 {
+        println!("date: clock_gettime failed");
+        return 1;
+    }
+    let (year, month, day) = civil_from_days(tp.tv_sec.div_euclid(86400));
+    let secs = tp.tv_sec.rem_euclid(86400);
+    println!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+        year,
+        month,
+        day,
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60
+    );
+    0
+}
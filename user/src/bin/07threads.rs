@@ -0,0 +1,56 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::ptr::addr_of_mut;
+use user_lib::{
+    dump_vm, exit, gettid, mutex_create, mutex_lock, mutex_unlock, thread_create, waittid, yield_,
+    DUMP_AREAS, DUMP_PAGE_TABLE,
+};
+
+const THREADS: usize = 3;
+const ROUNDS: usize = 100;
+
+// 所有线程共享的计数器，由互斥锁保护
+static mut COUNTER: usize = 0;
+static mut MUTEX: usize = 0;
+
+fn worker(id: usize) -> ! {
+    for _ in 0..ROUNDS {
+        unsafe {
+            mutex_lock(MUTEX);
+            // 在读和写之间让出 CPU，没有锁的话其他线程的修改会被覆盖
+            let value = *addr_of_mut!(COUNTER);
+            yield_();
+            *addr_of_mut!(COUNTER) = value + 1;
+            mutex_unlock(MUTEX);
+        }
+    }
+    println!("threads: worker {} (tid {}) done", id, gettid());
+    exit(id as i32)
+}
+
+#[no_mangle]
+fn main() -> i32 {
+    unsafe { MUTEX = mutex_create() as usize };
+    let mut tids = [0; THREADS];
+    for (id, tid) in tids.iter_mut().enumerate() {
+        *tid = thread_create(worker, id) as usize;
+    }
+    // 每个线程有自己的用户栈和 TrapContext
+    dump_vm(DUMP_AREAS | DUMP_PAGE_TABLE);
+    for tid in tids {
+        let mut exit_code = 0;
+        waittid(tid, &mut exit_code);
+        println!("threads: tid {} exited with code {}", tid, exit_code);
+    }
+    let counter = unsafe { *addr_of_mut!(COUNTER) };
+    println!(
+        "threads: counter = {} (expected {})",
+        counter,
+        THREADS * ROUNDS
+    );
+    0
+}
@@ -0,0 +1,115 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::ptr::addr_of_mut;
+use core::sync::atomic::{AtomicU32, Ordering};
+use user_lib::{
+    exit, futex_cmp_requeue, futex_wait, futex_wake, get_time_ms, sleep_ms, thread_create, waittid,
+    yield_, TimeSpec, EAGAIN, ETIMEDOUT,
+};
+
+const THREADS: usize = 3;
+const ROUNDS: usize = 100;
+
+/// 用户态的互斥锁：0 未上锁，1 上锁且没有等待者，2 上锁且可能有等待者
+struct FutexLock {
+    state: AtomicU32,
+}
+
+impl FutexLock {
+    const fn new() -> Self {
+        Self {
+            state: AtomicU32::new(0),
+        }
+    }
+
+    fn lock(&self) {
+        if self
+            .state
+            .compare_exchange(0, 1, Ordering::Acquire, Ordering::Relaxed)
+            .is_ok()
+        {
+            return;
+        }
+        // 有竞争：标记有等待者后睡眠，醒来重新抢锁
+        while self.state.swap(2, Ordering::Acquire) != 0 {
+            futex_wait(&self.state, 2, None);
+        }
+    }
+
+    fn unlock(&self) {
+        if self.state.swap(0, Ordering::Release) == 2 {
+            futex_wake(&self.state, 1);
+        }
+    }
+}
+
+static LOCK: FutexLock = FutexLock::new();
+static mut COUNTER: usize = 0;
+
+// requeue 测试：线程在 GATE 上等待，被移到 TARGET 上之后由 TARGET 唤醒
+static GATE: AtomicU32 = AtomicU32::new(0);
+static TARGET: AtomicU32 = AtomicU32::new(0);
+
+fn worker(_: usize) -> ! {
+    for _ in 0..ROUNDS {
+        LOCK.lock();
+        unsafe {
+            let value = *addr_of_mut!(COUNTER);
+            yield_();
+            *addr_of_mut!(COUNTER) = value + 1;
+        }
+        LOCK.unlock();
+    }
+    exit(0)
+}
+
+fn gate_waiter(_: usize) -> ! {
+    exit(futex_wait(&GATE, 0, None) as i32)
+}
+
+#[no_mangle]
+fn main() -> i32 {
+    // 1. 用 futex 实现的锁保护计数器
+    let tids: [usize; THREADS] = core::array::from_fn(|_| thread_create(worker, 0) as usize);
+    for tid in tids {
+        waittid(tid, &mut 0);
+    }
+    let counter = unsafe { *addr_of_mut!(COUNTER) };
+    println!(
+        "futex: counter = {} (expected {})",
+        counter,
+        THREADS * ROUNDS
+    );
+
+    // 2. 值不相等时立即返回 EAGAIN，相等时等到超时返回 ETIMEDOUT
+    let futex = AtomicU32::new(1);
+    assert_eq!(futex_wait(&futex, 0, None), -EAGAIN);
+    let start = get_time_ms();
+    let timeout = TimeSpec {
+        tv_sec: 0,
+        tv_nsec: 50_000_000,
+    };
+    assert_eq!(futex_wait(&futex, 1, Some(&timeout)), -ETIMEDOUT);
+    println!("futex: timed out after {} ms", get_time_ms() - start);
+
+    // 3. 唤醒一个 GATE 上的等待者，把其余的移到 TARGET 上
+    let tids: [usize; THREADS] = core::array::from_fn(|_| thread_create(gate_waiter, 0) as usize);
+    sleep_ms(50); // 等所有线程都开始等待
+    assert_eq!(futex_cmp_requeue(&GATE, 1, usize::MAX, &TARGET, 1), -EAGAIN);
+    let moved = futex_cmp_requeue(&GATE, 1, usize::MAX, &TARGET, 0);
+    let woken = futex_wake(&TARGET, u32::MAX);
+    println!(
+        "futex: requeue returned {}, then woke {} on target",
+        moved, woken
+    );
+    for tid in tids {
+        let mut exit_code = -1;
+        waittid(tid, &mut exit_code);
+        assert_eq!(exit_code, 0);
+    }
+    0
+}
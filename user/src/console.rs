@@ -0,0 +1,56 @@
+use super::write;
+use core::fmt::{self, Write};
+
+const STDOUT: usize = 1;
+const LINE_BUFFER_SIZE: usize = 256;
+
+/// 先把格式化的结果攒在缓冲区中，满了或者 print 结束时才调用一次 write
+/// 这样一行输出只对应一次系统调用，不会和其他任务的输出交错
+struct Stdout {
+    buf: [u8; LINE_BUFFER_SIZE],
+    len: usize,
+}
+
+impl Stdout {
+    fn flush(&mut self) {
+        if self.len > 0 {
+            write(STDOUT, &self.buf[..self.len]);
+            self.len = 0;
+        }
+    }
+}
+
+impl Write for Stdout {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for &b in s.as_bytes() {
+            if self.len == LINE_BUFFER_SIZE {
+                self.flush();
+            }
+            self.buf[self.len] = b;
+            self.len += 1;
+        }
+        Ok(())
+    }
+}
+
+pub fn print(args: fmt::Arguments) {
+    let mut stdout = Stdout {
+        buf: [0; LINE_BUFFER_SIZE],
+        len: 0,
+    };
+    stdout.write_fmt(args).unwrap();
+    stdout.flush();
+}
+
+#[macro_export]
+macro_rules! print {
+    ($($arg:tt)*) => ({
+        $crate::console::print(format_args!($($arg)*));
+    });
+}
+
+#[macro_export]
+macro_rules! println {
+    () => ($crate::print!("\n"));
+    ($($arg:tt)*) => ($crate::print!("{}\n", format_args!($($arg)*)));
+}
@@ -0,0 +1,234 @@
+#![no_std]
+#![feature(linkage)]
+
+#[macro_use]
+pub mod console;
+mod lang_items;
+mod syscall;
+
+use core::sync::atomic::AtomicU32;
+use syscall::*;
+
+pub const STDIN: usize = 0;
+pub const STDOUT: usize = 1;
+
+/// 用户程序的入口，由内核在进入用户态时跳转到这里
+#[no_mangle]
+#[link_section = ".text.entry"]
+pub extern "C" fn _start() -> ! {
+    exit(main());
+}
+
+// 弱符号：真正的 main 由 src/bin 中的每个程序提供
+#[linkage = "weak"]
+#[no_mangle]
+fn main() -> i32 {
+    panic!("Cannot find main!");
+}
+
+// 终端设置，布局与 Linux 的 struct termios 一致
+pub const TCGETS: usize = 0x5401;
+pub const TCSETS: usize = 0x5402;
+pub const ISIG: u32 = 0o1; // 处理 Ctrl-C
+pub const ICANON: u32 = 0o2; // 规范模式：按行读取，支持行编辑
+pub const ECHO: u32 = 0o10; // 回显输入的字符
+
+#[derive(Debug, Default, Clone, Copy)]
+#[repr(C)]
+pub struct Termios {
+    pub c_iflag: u32,
+    pub c_oflag: u32,
+    pub c_cflag: u32,
+    pub c_lflag: u32,
+    pub c_line: u8,
+    pub c_cc: [u8; 19],
+}
+
+// open 的访问模式
+pub const O_RDONLY: u32 = 0;
+pub const O_WRONLY: u32 = 1;
+pub const O_RDWR: u32 = 2;
+
+/// path 必须以 '\0' 结尾，例如 "/dev/kmsg\0"
+pub fn open(path: &str, flags: u32) -> isize {
+    sys_openat(path, flags)
+}
+
+pub fn close(fd: usize) -> isize {
+    sys_close(fd)
+}
+
+pub fn read(fd: usize, buf: &mut [u8]) -> isize {
+    sys_read(fd, buf)
+}
+
+pub fn write(fd: usize, buf: &[u8]) -> isize {
+    sys_write(fd, buf)
+}
+
+pub fn exit(exit_code: i32) -> ! {
+    sys_exit(exit_code)
+}
+
+// 时钟
+pub const CLOCK_REALTIME: usize = 0;
+pub const CLOCK_MONOTONIC: usize = 1;
+
+#[derive(Debug, Default, Clone, Copy)]
+#[repr(C)]
+pub struct TimeSpec {
+    pub tv_sec: i64,
+    pub tv_nsec: i64,
+}
+
+pub fn clock_gettime(clock_id: usize, tp: &mut TimeSpec) -> isize {
+    sys_clock_gettime(clock_id, tp)
+}
+
+pub fn clock_settime(clock_id: usize, tp: &TimeSpec) -> isize {
+    sys_clock_settime(clock_id, tp)
+}
+
+pub fn nanosleep(req: &TimeSpec) -> isize {
+    sys_nanosleep(req)
+}
+
+/// 单调时钟的当前时间 (毫秒)
+pub fn get_time_ms() -> i64 {
+    let mut tp = TimeSpec::default();
+    clock_gettime(CLOCK_MONOTONIC, &mut tp);
+    tp.tv_sec * 1000 + tp.tv_nsec / 1_000_000
+}
+
+pub fn sleep_ms(ms: u64) -> isize {
+    nanosleep(&TimeSpec {
+        tv_sec: (ms / 1000) as i64,
+        tv_nsec: (ms % 1000 * 1_000_000) as i64,
+    })
+}
+
+// syslog 的操作
+pub const SYSLOG_ACTION_READ_ALL: usize = 3;
+pub const SYSLOG_ACTION_READ_CLEAR: usize = 4;
+pub const SYSLOG_ACTION_CLEAR: usize = 5;
+pub const SYSLOG_ACTION_SIZE_BUFFER: usize = 10;
+
+/// 读取内核日志缓冲区，返回读到的字节数
+pub fn syslog(action: usize, buf: &mut [u8]) -> isize {
+    sys_syslog(action, buf)
+}
+
+pub fn yield_() -> isize {
+    sys_yield()
+}
+
+pub fn tcgetattr(fd: usize, termios: &mut Termios) -> isize {
+    sys_ioctl(fd, TCGETS, termios as *mut Termios as usize)
+}
+
+pub fn tcsetattr(fd: usize, termios: &Termios) -> isize {
+    sys_ioctl(fd, TCSETS, termios as *const Termios as usize)
+}
+
+// 线程
+/// 创建线程，从 entry(arg) 开始执行，返回线程编号 (tid)
+/// 线程函数不能返回，结束时要调用 exit；主线程 exit 时整个进程退出
+pub fn thread_create(entry: fn(usize) -> !, arg: usize) -> isize {
+    sys_thread_create(entry as usize, arg)
+}
+
+pub fn gettid() -> isize {
+    sys_gettid()
+}
+
+/// 等待线程 tid 退出，把它的退出码写入 exit_code
+pub fn waittid(tid: usize, exit_code: &mut i32) -> isize {
+    sys_waittid(tid, exit_code)
+}
+
+// 同步原语，返回的编号只在当前进程中有效，进程中的线程共享
+pub fn mutex_create() -> isize {
+    sys_mutex_create()
+}
+
+pub fn mutex_lock(id: usize) -> isize {
+    sys_mutex_lock(id)
+}
+
+pub fn mutex_unlock(id: usize) -> isize {
+    sys_mutex_unlock(id)
+}
+
+pub fn semaphore_create(count: usize) -> isize {
+    sys_semaphore_create(count)
+}
+
+pub fn semaphore_up(id: usize) -> isize {
+    sys_semaphore_up(id)
+}
+
+pub fn semaphore_down(id: usize) -> isize {
+    sys_semaphore_down(id)
+}
+
+pub fn condvar_create() -> isize {
+    sys_condvar_create()
+}
+
+pub fn condvar_signal(id: usize) -> isize {
+    sys_condvar_signal(id)
+}
+
+/// 调用者必须持有 mutex_id，返回时重新持有它
+pub fn condvar_wait(id: usize, mutex_id: usize) -> isize {
+    sys_condvar_wait(id, mutex_id)
+}
+
+// futex：用户态的锁在没有竞争时只需要原子操作，需要等待时才进入内核
+// 内核以变量的物理地址区分不同的 futex，映射了同一物理页的进程之间也可以使用
+pub const FUTEX_WAIT: usize = 0;
+pub const FUTEX_WAKE: usize = 1;
+pub const FUTEX_CMP_REQUEUE: usize = 4;
+
+// futex 返回的错误码
+pub const EAGAIN: isize = 11;
+pub const ETIMEDOUT: isize = 110;
+
+/// *futex == val 时睡眠，直到被 futex_wake 唤醒或者经过 timeout (相对时间)
+/// 返回 0 表示被唤醒，-EAGAIN 表示值已经变了，-ETIMEDOUT 表示超时
+pub fn futex_wait(futex: &AtomicU32, val: u32, timeout: Option<&TimeSpec>) -> isize {
+    let timeout = timeout.map_or(0, |timeout| timeout as *const TimeSpec as usize);
+    sys_futex(futex, FUTEX_WAIT, val, timeout, None, 0)
+}
+
+/// 唤醒最多 count 个等待 futex 的任务，返回唤醒的个数
+pub fn futex_wake(futex: &AtomicU32, count: u32) -> isize {
+    sys_futex(futex, FUTEX_WAKE, count, 0, None, 0)
+}
+
+/// *futex == val 时唤醒最多 wake_count 个等待 futex 的任务，再把最多 requeue_count 个移到 target 上等待
+/// 返回唤醒和移动的总数，值已经变了时返回 -EAGAIN
+pub fn futex_cmp_requeue(
+    futex: &AtomicU32,
+    wake_count: u32,
+    requeue_count: usize,
+    target: &AtomicU32,
+    val: u32,
+) -> isize {
+    sys_futex(
+        futex,
+        FUTEX_CMP_REQUEUE,
+        wake_count,
+        requeue_count,
+        Some(target),
+        val,
+    )
+}
+
+// 调试：把当前进程的地址空间打印到内核控制台，可以同时指定多个
+pub const DUMP_AREAS: usize = 1 << 0; // 地址空间中的各个区域
+pub const DUMP_PAGE_TABLE: usize = 1 << 1; // 页表中所有有效的页表项
+
+pub fn dump_vm(what: usize) -> isize {
+    sys_dump_vm(what)
+}
@@ -0,0 +1,201 @@
+use super::TimeSpec;
+use core::arch::asm;
+use core::sync::atomic::AtomicU32;
+
+// 系统调用号，与 Linux (RISC-V) 保持一致
+const SYSCALL_IOCTL: usize = 29;
+const SYSCALL_OPENAT: usize = 56;
+const SYSCALL_CLOSE: usize = 57;
+const SYSCALL_READ: usize = 63;
+const SYSCALL_WRITE: usize = 64;
+const SYSCALL_EXIT: usize = 93;
+const SYSCALL_FUTEX: usize = 98;
+const SYSCALL_NANOSLEEP: usize = 101;
+const SYSCALL_CLOCK_SETTIME: usize = 112;
+const SYSCALL_CLOCK_GETTIME: usize = 113;
+const SYSCALL_SYSLOG: usize = 116;
+const SYSCALL_YIELD: usize = 124;
+const SYSCALL_GETTID: usize = 178;
+// 线程和同步原语，编号与 rCore 相同
+const SYSCALL_THREAD_CREATE: usize = 1000;
+const SYSCALL_WAITTID: usize = 1002;
+const SYSCALL_MUTEX_CREATE: usize = 1010;
+const SYSCALL_MUTEX_LOCK: usize = 1011;
+const SYSCALL_MUTEX_UNLOCK: usize = 1012;
+const SYSCALL_SEMAPHORE_CREATE: usize = 1020;
+const SYSCALL_SEMAPHORE_UP: usize = 1021;
+const SYSCALL_SEMAPHORE_DOWN: usize = 1022;
+const SYSCALL_CONDVAR_CREATE: usize = 1030;
+const SYSCALL_CONDVAR_SIGNAL: usize = 1031;
+const SYSCALL_CONDVAR_WAIT: usize = 1032;
+// 调试用
+const SYSCALL_DUMP_VM: usize = 1100;
+
+// openat 的 dirfd：相对于当前目录
+const AT_FDCWD: isize = -100;
+
+/// 通过 ecall 陷入内核，a7 放系统调用号，a0-a2 放参数，返回值在 a0 中
+fn syscall(id: usize, args: [usize; 3]) -> isize {
+    let mut ret: isize;
+    unsafe {
+        asm!(
+            "ecall",
+            inlateout("a0") args[0] => ret,
+            in("a1") args[1],
+            in("a2") args[2],
+            in("a7") id
+        );
+    }
+    ret
+}
+
+/// 有 6 个参数的系统调用，参数放在 a0-a5 中
+fn syscall6(id: usize, args: [usize; 6]) -> isize {
+    let mut ret: isize;
+    unsafe {
+        asm!(
+            "ecall",
+            inlateout("a0") args[0] => ret,
+            in("a1") args[1],
+            in("a2") args[2],
+            in("a3") args[3],
+            in("a4") args[4],
+            in("a5") args[5],
+            in("a7") id
+        );
+    }
+    ret
+}
+
+pub fn sys_ioctl(fd: usize, request: usize, arg: usize) -> isize {
+    syscall(SYSCALL_IOCTL, [fd, request, arg])
+}
+
+pub fn sys_openat(path: &str, flags: u32) -> isize {
+    syscall(
+        SYSCALL_OPENAT,
+        [AT_FDCWD as usize, path.as_ptr() as usize, flags as usize],
+    )
+}
+
+pub fn sys_close(fd: usize) -> isize {
+    syscall(SYSCALL_CLOSE, [fd, 0, 0])
+}
+
+pub fn sys_read(fd: usize, buffer: &mut [u8]) -> isize {
+    syscall(
+        SYSCALL_READ,
+        [fd, buffer.as_mut_ptr() as usize, buffer.len()],
+    )
+}
+
+pub fn sys_write(fd: usize, buffer: &[u8]) -> isize {
+    syscall(SYSCALL_WRITE, [fd, buffer.as_ptr() as usize, buffer.len()])
+}
+
+pub fn sys_exit(exit_code: i32) -> ! {
+    syscall(SYSCALL_EXIT, [exit_code as usize, 0, 0]);
+    unreachable!("sys_exit never returns!");
+}
+
+pub fn sys_futex(
+    uaddr: &AtomicU32,
+    op: usize,
+    val: u32,
+    timeout_or_val2: usize,
+    uaddr2: Option<&AtomicU32>,
+    val3: u32,
+) -> isize {
+    syscall6(
+        SYSCALL_FUTEX,
+        [
+            uaddr.as_ptr() as usize,
+            op,
+            val as usize,
+            timeout_or_val2,
+            uaddr2.map_or(0, |uaddr2| uaddr2.as_ptr() as usize),
+            val3 as usize,
+        ],
+    )
+}
+
+pub fn sys_nanosleep(req: &TimeSpec) -> isize {
+    syscall(SYSCALL_NANOSLEEP, [req as *const TimeSpec as usize, 0, 0])
+}
+
+pub fn sys_clock_settime(clock_id: usize, tp: &TimeSpec) -> isize {
+    syscall(
+        SYSCALL_CLOCK_SETTIME,
+        [clock_id, tp as *const TimeSpec as usize, 0],
+    )
+}
+
+pub fn sys_clock_gettime(clock_id: usize, tp: &mut TimeSpec) -> isize {
+    syscall(
+        SYSCALL_CLOCK_GETTIME,
+        [clock_id, tp as *mut TimeSpec as usize, 0],
+    )
+}
+
+pub fn sys_syslog(action: usize, buffer: &mut [u8]) -> isize {
+    syscall(
+        SYSCALL_SYSLOG,
+        [action, buffer.as_mut_ptr() as usize, buffer.len()],
+    )
+}
+
+pub fn sys_yield() -> isize {
+    syscall(SYSCALL_YIELD, [0, 0, 0])
+}
+
+pub fn sys_gettid() -> isize {
+    syscall(SYSCALL_GETTID, [0, 0, 0])
+}
+
+pub fn sys_thread_create(entry: usize, arg: usize) -> isize {
+    syscall(SYSCALL_THREAD_CREATE, [entry, arg, 0])
+}
+
+pub fn sys_waittid(tid: usize, exit_code: &mut i32) -> isize {
+    syscall(SYSCALL_WAITTID, [tid, exit_code as *mut i32 as usize, 0])
+}
+
+pub fn sys_mutex_create() -> isize {
+    syscall(SYSCALL_MUTEX_CREATE, [0, 0, 0])
+}
+
+pub fn sys_mutex_lock(id: usize) -> isize {
+    syscall(SYSCALL_MUTEX_LOCK, [id, 0, 0])
+}
+
+pub fn sys_mutex_unlock(id: usize) -> isize {
+    syscall(SYSCALL_MUTEX_UNLOCK, [id, 0, 0])
+}
+
+pub fn sys_semaphore_create(count: usize) -> isize {
+    syscall(SYSCALL_SEMAPHORE_CREATE, [count, 0, 0])
+}
+
+pub fn sys_semaphore_up(id: usize) -> isize {
+    syscall(SYSCALL_SEMAPHORE_UP, [id, 0, 0])
+}
+
+pub fn sys_semaphore_down(id: usize) -> isize {
+    syscall(SYSCALL_SEMAPHORE_DOWN, [id, 0, 0])
+}
+
+pub fn sys_condvar_create() -> isize {
+    syscall(SYSCALL_CONDVAR_CREATE, [0, 0, 0])
+}
+
+pub fn sys_condvar_signal(id: usize) -> isize {
+    syscall(SYSCALL_CONDVAR_SIGNAL, [id, 0, 0])
+}
+
+pub fn sys_condvar_wait(id: usize, mutex_id: usize) -> isize {
+    syscall(SYSCALL_CONDVAR_WAIT, [id, mutex_id, 0])
+}
+
+pub fn sys_dump_vm(what: usize) -> isize {
+    syscall(SYSCALL_DUMP_VM, [what, 0, 0])
+}